@@ -0,0 +1,198 @@
+use crate::index::{check_version, VERSION};
+use crate::tag::details::TagDetails;
+use memmap2::Mmap;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub const MAGIC: &[u8] = b"PLMKIDX";
+
+type StringId = u32;
+
+/// Compact representation of an index: every tag value is stored once in `strings` and songs only
+/// keep the position of their values, since artists, albums and genres repeat a lot in a library.
+/// Strings are borrowed from the memory mapped index, so they are only copied once, into the songs
+#[derive(Serialize, Deserialize)]
+struct BinaryIndex<'a> {
+    /// Kept first, so `check_version` can read it without decoding the rest
+    version: u32,
+    root: &'a str,
+    #[serde(borrow)]
+    strings: Vec<&'a str>,
+    #[serde(borrow)]
+    songs: Vec<BinarySong<'a>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinarySong<'a> {
+    path: &'a str,
+    track: Option<StringId>,
+    title: Option<StringId>,
+    artist: Option<StringId>,
+    album: Option<StringId>,
+    album_artist: Option<StringId>,
+    year: Option<StringId>,
+    genre: Option<StringId>,
+    disc: Option<StringId>,
+    mb_recording_id: Option<StringId>,
+    mb_release_id: Option<StringId>,
+    mb_release_group_id: Option<StringId>,
+    mb_artist_id: Option<StringId>,
+    duration: Option<u64>,
+    modified: Option<u64>,
+    size: Option<u64>,
+}
+
+#[derive(Default)]
+struct Interner<'a> {
+    strings: Vec<&'a str>,
+    ids: HashMap<&'a str, StringId>,
+}
+
+impl<'a> Interner<'a> {
+    fn intern(&mut self, value: &'a Option<String>) -> Option<StringId> {
+        let value = value.as_deref()?;
+        if let Some(id) = self.ids.get(value) {
+            return Some(*id);
+        }
+        let id = self.strings.len() as StringId;
+        self.strings.push(value);
+        self.ids.insert(value, id);
+        Some(id)
+    }
+}
+
+pub fn write(file: &Path, root: &str, songs: &[TagDetails]) -> Option<()> {
+    let mut interner = Interner::default();
+    let songs = songs
+        .iter()
+        .map(|song| BinarySong {
+            path: &song.path,
+            track: interner.intern(&song.track),
+            title: interner.intern(&song.title),
+            artist: interner.intern(&song.artist),
+            album: interner.intern(&song.album),
+            album_artist: interner.intern(&song.album_artist),
+            year: interner.intern(&song.year),
+            genre: interner.intern(&song.genre),
+            disc: interner.intern(&song.disc),
+            mb_recording_id: interner.intern(&song.mb_recording_id),
+            mb_release_id: interner.intern(&song.mb_release_id),
+            mb_release_group_id: interner.intern(&song.mb_release_group_id),
+            mb_artist_id: interner.intern(&song.mb_artist_id),
+            duration: song.duration,
+            modified: song.modified,
+            size: song.size,
+        })
+        .collect::<Vec<BinarySong>>();
+    let index = BinaryIndex {
+        version: VERSION,
+        root,
+        strings: interner.strings,
+        songs,
+    };
+
+    let mut writer = BufWriter::new(File::create(file).ok()?);
+    writer.write_all(MAGIC).ok()?;
+    bincode::serialize_into(&mut writer, &index).ok()?;
+    writer.flush().ok()
+}
+
+pub fn read(path: &Path) -> Option<(Option<String>, Vec<TagDetails>)> {
+    let file = File::open(path).ok()?;
+    // SAFETY: the index is only read, it is up to the user to not modify it while a query runs
+    let mmap = unsafe { Mmap::map(&file) }.ok()?;
+    let bytes = mmap.strip_prefix(MAGIC)?;
+    check_version(path, bytes)?;
+    let index = bincode::deserialize::<BinaryIndex>(bytes).ok()?;
+
+    let value = |id: Option<StringId>| Some(index.strings.get(id? as usize)?.to_string());
+    let songs = index
+        .songs
+        .par_iter()
+        .map(|song| TagDetails {
+            path: song.path.to_string(),
+            track: value(song.track),
+            title: value(song.title),
+            artist: value(song.artist),
+            album: value(song.album),
+            album_artist: value(song.album_artist),
+            year: value(song.year),
+            genre: value(song.genre),
+            disc: value(song.disc),
+            mb_recording_id: value(song.mb_recording_id),
+            mb_release_id: value(song.mb_release_id),
+            mb_release_group_id: value(song.mb_release_group_id),
+            mb_artist_id: value(song.mb_artist_id),
+            duration: song.duration,
+            modified: song.modified,
+            size: song.size,
+        })
+        .collect::<Vec<TagDetails>>();
+    Some((Some(index.root.to_string()), songs))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::binary::{read, write};
+    use crate::tag::details::TagDetails;
+    use std::path::Path;
+
+    #[test]
+    fn ensure_fn_read_works_as_expected_1() {
+        let file = std::env::temp_dir().join("playlist-maker-binary-read-1.bin");
+        let songs = default_songs();
+
+        write(&file, "/home/a/Music", &songs).unwrap();
+        let (root, read) = read(&file).unwrap();
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(root.as_deref(), Some("/home/a/Music"));
+        assert_eq!(read.len(), 3);
+        for (song, expected) in read.iter().zip(songs.iter()) {
+            assert_eq!(song.path, expected.path);
+            assert_eq!(song.artist, expected.artist);
+            assert_eq!(song.album, expected.album);
+            assert_eq!(song.genre, expected.genre);
+            assert_eq!(song.track, expected.track);
+            assert_eq!(song.duration, expected.duration);
+            assert_eq!(song.modified, expected.modified);
+        }
+    }
+
+    #[test]
+    fn ensure_fn_read_works_as_expected_2() {
+        let output = read(Path::new("test-data/index.csv"));
+
+        assert!(output.is_none());
+    }
+
+    fn default_songs() -> Vec<TagDetails> {
+        let info1 = TagDetails {
+            path: "test-data/songs/1.mp3".to_string(),
+            artist: Some("Camila Cabello".to_string()),
+            album: Some("Camila".to_string()),
+            genre: Some("Pop".to_string()),
+            track: Some("1".to_string()),
+            duration: Some(215),
+            modified: Some(1676678400),
+            ..Default::default()
+        };
+        let info2 = TagDetails {
+            path: "test-data/songs/2.mp3".to_string(),
+            artist: Some("Camila Cabello".to_string()),
+            album: Some("Camila".to_string()),
+            genre: Some("Pop".to_string()),
+            track: Some("2".to_string()),
+            ..Default::default()
+        };
+        let info3 = TagDetails {
+            path: "test-data/songs/3.mp3".to_string(),
+            genre: Some("Rap".to_string()),
+            ..Default::default()
+        };
+        vec![info1, info2, info3]
+    }
+}
@@ -0,0 +1,168 @@
+use crate::index::{check_version, VERSION};
+use crate::tag::details::TagDetails;
+use crate::tag::r#type::TagType;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub const MAGIC: &[u8] = b"PLMKINV";
+
+/// Position of a song in the index
+pub type SongId = u32;
+
+/// Tags with postings, the path is left out since every song has a different one
+const INDEXED_TAGS: [TagType; 12] = [
+    TagType::Title,
+    TagType::Artist,
+    TagType::Album,
+    TagType::AlbumArtist,
+    TagType::Date,
+    TagType::Genre,
+    TagType::Disc,
+    TagType::Track,
+    TagType::MbRecordingId,
+    TagType::MbReleaseId,
+    TagType::MbReleaseGroupId,
+    TagType::MbArtistId,
+];
+
+/// For each tag, the songs (by their position in the index) that have each of its values
+#[derive(Default, Serialize, Deserialize)]
+pub struct Postings {
+    tags: HashMap<TagType, BTreeMap<String, Vec<SongId>>>,
+}
+
+impl Postings {
+    pub fn new(songs: &[TagDetails]) -> Self {
+        let mut postings = Postings::default();
+        for (id, song) in songs.iter().enumerate() {
+            for tag in INDEXED_TAGS.iter() {
+                if let Some(value) = tag.collect(song) {
+                    postings
+                        .tags
+                        .entry(*tag)
+                        .or_default()
+                        .entry(value.to_owned())
+                        .or_default()
+                        .push(id as SongId);
+                }
+            }
+        }
+        postings
+    }
+
+    pub fn values(&self, tag: &TagType) -> Option<&BTreeMap<String, Vec<SongId>>> {
+        if INDEXED_TAGS.contains(tag) {
+            Some(self.tags.get(tag).unwrap_or(&EMPTY))
+        } else {
+            None
+        }
+    }
+}
+
+static EMPTY: BTreeMap<String, Vec<SongId>> = BTreeMap::new();
+
+#[derive(Serialize, Deserialize)]
+struct InvertedIndex {
+    /// Kept first, so `check_version` can read it without decoding the rest
+    version: u32,
+    root: String,
+    songs: Vec<TagDetails>,
+    postings: Postings,
+}
+
+pub fn write(file: &Path, root: &str, songs: &[TagDetails]) -> Option<()> {
+    let index = InvertedIndex {
+        version: VERSION,
+        root: root.to_owned(),
+        songs: songs.to_vec(),
+        postings: Postings::new(songs),
+    };
+
+    let mut writer = BufWriter::new(File::create(file).ok()?);
+    writer.write_all(MAGIC).ok()?;
+    bincode::serialize_into(&mut writer, &index).ok()?;
+    writer.flush().ok()
+}
+
+pub fn read(path: &Path) -> Option<(Option<String>, Vec<TagDetails>, Postings)> {
+    // songs and postings are decoded into owned values, so the index is read at once
+    let content = fs::read(path).ok()?;
+    let bytes = content.strip_prefix(MAGIC)?;
+    check_version(path, bytes)?;
+    let index = bincode::deserialize::<InvertedIndex>(bytes).ok()?;
+    Some((Some(index.root), index.songs, index.postings))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::inverted::{read, write, Postings};
+    use crate::tag::details::TagDetails;
+    use crate::tag::r#type::TagType;
+    use std::path::Path;
+
+    #[test]
+    fn ensure_fn_values_works_as_expected_1() {
+        let postings = Postings::new(&default_songs());
+
+        let artists = postings.values(&TagType::Artist).unwrap();
+
+        assert_eq!(artists.len(), 2);
+        assert_eq!(artists.get("Camila Cabello").unwrap(), &vec![0, 1]);
+        assert_eq!(artists.get("Drake").unwrap(), &vec![2]);
+    }
+
+    #[test]
+    fn ensure_fn_values_works_as_expected_2() {
+        let postings = Postings::new(&default_songs());
+
+        assert!(postings.values(&TagType::Path).is_none());
+        assert!(postings.values(&TagType::MbArtistId).unwrap().is_empty());
+    }
+
+    #[test]
+    fn ensure_fn_read_works_as_expected_1() {
+        let file = std::env::temp_dir().join("playlist-maker-inverted-read-1.inv");
+
+        write(&file, "/home/a/Music", &default_songs()).unwrap();
+        let (root, songs, postings) = read(&file).unwrap();
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(root.as_deref(), Some("/home/a/Music"));
+        assert_eq!(songs.len(), 3);
+        assert_eq!(songs.get(2).unwrap().artist.as_deref(), Some("Drake"));
+        let genres = postings.values(&TagType::Genre).unwrap();
+        assert_eq!(genres.get("Pop").unwrap(), &vec![0, 1]);
+    }
+
+    #[test]
+    fn ensure_fn_read_works_as_expected_2() {
+        let output = read(Path::new("test-data/index.csv"));
+
+        assert!(output.is_none());
+    }
+
+    fn default_songs() -> Vec<TagDetails> {
+        let info1 = TagDetails {
+            path: "test-data/songs/1.mp3".to_string(),
+            artist: Some("Camila Cabello".to_string()),
+            genre: Some("Pop".to_string()),
+            ..Default::default()
+        };
+        let info2 = TagDetails {
+            path: "test-data/songs/2.mp3".to_string(),
+            artist: Some("Camila Cabello".to_string()),
+            genre: Some("Pop".to_string()),
+            ..Default::default()
+        };
+        let info3 = TagDetails {
+            path: "test-data/songs/3.mp3".to_string(),
+            artist: Some("Drake".to_string()),
+            genre: Some("Rap".to_string()),
+            ..Default::default()
+        };
+        vec![info1, info2, info3]
+    }
+}
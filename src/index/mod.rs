@@ -0,0 +1,647 @@
+pub mod binary;
+pub mod inverted;
+
+use crate::index::inverted::Postings;
+use crate::tag::details::TagDetails;
+use crate::utils;
+use crate::utils::paths::{absolute, common_root};
+use csv::StringRecord;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+/// Version of the index written by this build, bumped every time the meaning of the columns or the
+/// layout of binary and inverted indexes changes (v4 added the duration of the songs, v5 dropped
+/// the paths of the postings)
+pub const VERSION: u32 = 5;
+
+const VERSION_PREFIX: &str = "#playlist-maker-index v";
+
+const ROOT_PREFIX: &str = "#library-root ";
+
+#[derive(Debug, PartialEq)]
+pub enum IndexFormat {
+    Csv,
+    Binary,
+    Inverted,
+}
+
+pub fn version_line() -> String {
+    format!("{}{}", VERSION_PREFIX, VERSION)
+}
+
+pub fn root_line(root: &str) -> String {
+    format!("{}{}", ROOT_PREFIX, root)
+}
+
+/// Binary and inverted indexes are picked by extension, every other file is a csv index
+pub fn format(file: &Path) -> IndexFormat {
+    match file.extension().map(|e| e.to_ascii_lowercase()) {
+        Some(extension) if extension == "bin" => IndexFormat::Binary,
+        Some(extension) if extension == "inv" => IndexFormat::Inverted,
+        _ => IndexFormat::Csv,
+    }
+}
+
+pub fn read(file: &Path, music_root: Option<&Path>) -> Vec<TagDetails> {
+    read_with_postings(file, music_root).0
+}
+
+/// Reads an index of any format, only inverted indexes have postings.
+///
+/// Paths are stored relative to the library root recorded in the index, `music_root` can be used
+/// to load them from somewhere else (e.g. an index built in another machine).
+pub fn read_with_postings(
+    file: &Path,
+    music_root: Option<&Path>,
+) -> (Vec<TagDetails>, Option<Postings>) {
+    let (root, mut songs, postings) = match format(file) {
+        IndexFormat::Csv => {
+            let (root, songs) = read_csv(file).unwrap_or_else(|| invalid(file));
+            (root, songs, None)
+        }
+        IndexFormat::Binary => {
+            let (root, songs) = binary::read(file).unwrap_or_else(|| invalid(file));
+            (root, songs, None)
+        }
+        IndexFormat::Inverted => {
+            let (root, songs, postings) = inverted::read(file).unwrap_or_else(|| invalid(file));
+            (root, songs, Some(postings))
+        }
+    };
+
+    if let Some(rebase) = Rebase::new(root, music_root, &songs) {
+        songs
+            .iter_mut()
+            .for_each(|song| song.path = rebase.apply(&song.path));
+    }
+    (songs, postings)
+}
+
+/// Writes the binary and inverted indexes, csv ones are written by the `Printer`
+pub fn write(file: &Path, library_root: Option<&Path>, songs: &[TagDetails]) -> Option<()> {
+    match format(file) {
+        IndexFormat::Csv => None,
+        index_format => write_as(file, index_format, library_root, songs),
+    }
+}
+
+/// Writes an index in the given format whatever the extension of `file` is
+pub fn write_as(
+    file: &Path,
+    index_format: IndexFormat,
+    library_root: Option<&Path>,
+    songs: &[TagDetails],
+) -> Option<()> {
+    match index_format {
+        IndexFormat::Csv => std::fs::write(file, format!("{}\n", to_csv(library_root, songs))).ok(),
+        IndexFormat::Binary => {
+            let (root, songs) = relativize(songs, library_root);
+            binary::write(file, &root, &songs)
+        }
+        IndexFormat::Inverted => {
+            let (root, songs) = relativize(songs, library_root);
+            inverted::write(file, &root, &songs)
+        }
+    }
+}
+
+/// Csv index of the songs, with their paths relative to the library root recorded in it
+pub fn to_csv(library_root: Option<&Path>, songs: &[TagDetails]) -> String {
+    let (root, songs) = relativize(songs, library_root);
+    format!(
+        "{}\n{}\n{}",
+        version_line(),
+        root_line(&root),
+        csv_rows(&songs)
+    )
+}
+
+/// Header and rows of a csv with every field of the songs, as written in csv indexes
+pub fn csv_rows(songs: &[TagDetails]) -> String {
+    utils::csv::to_string(&TagDetails::FIELDS, songs)
+}
+
+/// Splits the paths of the songs in the library root (the deepest directory that contains all of
+/// them when it isn't known) and the paths relative to it
+pub fn relativize(songs: &[TagDetails], library_root: Option<&Path>) -> (String, Vec<TagDetails>) {
+    let root = library_root
+        .map(absolute)
+        .or_else(|| common_root(songs.iter().map(|song| song.path.as_str())))
+        .unwrap_or_default();
+    let songs = songs
+        .iter()
+        .map(|song| TagDetails {
+            path: absolute(Path::new(&song.path))
+                .strip_prefix(&root)
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_else(|_| song.path.to_owned()),
+            ..song.to_owned()
+        })
+        .collect::<Vec<TagDetails>>();
+    (root.to_string_lossy().to_string(), songs)
+}
+
+/// How the paths stored in an index are turned into the paths of the songs
+struct Rebase {
+    from: Option<PathBuf>,
+    to: PathBuf,
+}
+
+impl Rebase {
+    /// Indexes without a recorded root (before v3) store the full path of the songs, in that case
+    /// the deepest directory shared by all of them is the one replaced by `music_root`
+    fn new(root: Option<String>, music_root: Option<&Path>, songs: &[TagDetails]) -> Option<Self> {
+        match (root, music_root) {
+            (Some(root), music_root) => Some(Rebase {
+                from: None,
+                to: music_root.map_or_else(|| PathBuf::from(root), |dir| dir.to_path_buf()),
+            }),
+            (None, Some(music_root)) => Some(Rebase {
+                from: Some(common_root(songs.iter().map(|song| song.path.as_str()))?),
+                to: music_root.to_path_buf(),
+            }),
+            (None, None) => None,
+        }
+    }
+
+    fn apply(&self, path: &str) -> String {
+        let relative = match &self.from {
+            None => PathBuf::from(path),
+            Some(from) => absolute(Path::new(path))
+                .strip_prefix(from)
+                .map(|path| path.to_path_buf())
+                .unwrap_or_else(|_| PathBuf::from(path)),
+        };
+        self.to.join(relative).to_string_lossy().to_string()
+    }
+}
+
+/// Library root recorded in an index, read without loading its songs
+pub fn read_root(file: &Path) -> Option<String> {
+    match format(file) {
+        IndexFormat::Csv => {
+            let mut reader = BufReader::new(File::open(file).ok()?);
+            let mut line = String::new();
+            while reader.read_line(&mut line).ok()? > 0 {
+                if let Some(root) = parse_root(&line) {
+                    return Some(root);
+                }
+                parse_version(&line)?;
+                line.clear();
+            }
+            None
+        }
+        IndexFormat::Binary => read_binary_root(file, binary::MAGIC),
+        IndexFormat::Inverted => read_binary_root(file, inverted::MAGIC),
+    }
+}
+
+/// Binary and inverted indexes start with their version and root after the magic bytes
+fn read_binary_root(file: &Path, magic: &[u8]) -> Option<String> {
+    let mut reader = BufReader::new(File::open(file).ok()?);
+    let mut header = vec![0; magic.len()];
+    reader.read_exact(&mut header).ok()?;
+    if header != magic {
+        return None;
+    }
+    let (_, root) = bincode::deserialize_from::<_, (u32, String)>(reader).ok()?;
+    Some(root)
+}
+
+fn invalid<T>(file: &Path) -> T {
+    println!("index `{}` is not valid!", file.display());
+    exit(2);
+}
+
+/// Checks the version of a binary or inverted index before decoding the rest of it, since the
+/// layout of the songs changes between versions and can't be migrated like csv columns
+pub fn check_version(file: &Path, index: &[u8]) -> Option<()> {
+    let version = stored_version(index)?;
+    if version != VERSION {
+        println!(
+            "index `{}` was written by {} version of playlist-maker (v{}), rebuild it or convert it \
+             from a csv index!",
+            file.display(),
+            if version > VERSION { "a newer" } else { "an older" },
+            version
+        );
+        exit(2);
+    }
+    Some(())
+}
+
+/// Version of a binary or inverted index, the first field after its magic bytes
+fn stored_version(index: &[u8]) -> Option<u32> {
+    bincode::deserialize::<u32>(index).ok()
+}
+
+/// Reads a csv index written by any version of playlist-maker.
+///
+/// Columns are mapped by their header name, so unknown columns are ignored and missing ones are
+/// left empty. Indexes without a version line are the original 9 column format (v1). `None` when
+/// the index can't be read.
+fn read_csv(file: &Path) -> Option<(Option<String>, Vec<TagDetails>)> {
+    let mut reader = BufReader::new(File::open(file).ok()?);
+    let mut version = 1;
+    let mut root = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line).ok()?;
+        if let Some(line_version) = parse_version(&line) {
+            version = line_version;
+        } else if let Some(line_root) = parse_root(&line) {
+            root = Some(line_root);
+        } else {
+            break;
+        }
+    }
+    let content = Cursor::new(line).chain(reader);
+
+    if version > VERSION {
+        println!(
+            "index `{}` was written by a newer version of playlist-maker (v{})!",
+            file.display(),
+            version
+        );
+        exit(2);
+    }
+
+    let mut csv = csv::ReaderBuilder::new()
+        .delimiter(b',')
+        .double_quote(true)
+        .has_headers(true)
+        .from_reader(content);
+
+    let headers = migrate(version, csv.headers().ok()?);
+    if !headers.iter().any(|column| column == "path") {
+        println!("index `{}` has no path column!", file.display());
+        exit(2);
+    }
+    csv.set_headers(headers);
+
+    let (songs, skipped) =
+        csv.deserialize::<TagDetails>()
+            .fold((vec![], 0), |(mut songs, skipped), record| match record {
+                Ok(song) => {
+                    songs.push(song);
+                    (songs, skipped)
+                }
+                Err(_) => (songs, skipped + 1),
+            });
+    if skipped > 0 {
+        eprintln!(
+            "warning: {} rows of index `{}` are not valid and were skipped",
+            skipped,
+            file.display()
+        );
+    }
+    Some((root, songs))
+}
+
+fn parse_version(line: &str) -> Option<u32> {
+    line.trim_end().strip_prefix(VERSION_PREFIX)?.parse().ok()
+}
+
+fn parse_root(line: &str) -> Option<String> {
+    Some(
+        line.trim_end_matches(['\r', '\n'])
+            .strip_prefix(ROOT_PREFIX)?
+            .to_string(),
+    )
+}
+
+/// Maps the column names of an index written with `version` to the ones used by `TagDetails`
+fn migrate(version: u32, headers: &StringRecord) -> StringRecord {
+    headers
+        .iter()
+        .map(|column| column.trim().to_lowercase())
+        .map(|column| match (version, column.as_str()) {
+            // v1 indexes were sometimes edited by hand or by other tools, so the tag names
+            // accepted by the query language are also accepted as column names
+            (1, "albumartist") => String::from("album_artist"),
+            (1, "date") => String::from("year"),
+            (1, "tracknumber") => String::from("track"),
+            (1, "discnumber") => String::from("disc"),
+            _ => column,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::{
+        format, migrate, parse_version, read, read_csv, read_root, relativize, stored_version,
+        to_csv, version_line, IndexFormat, VERSION,
+    };
+    use crate::tag::details::TagDetails;
+    use crate::utils::paths::absolute;
+    use csv::StringRecord;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn ensure_fn_parse_version_works_as_expected_1() {
+        assert_eq!(parse_version(&version_line()), Some(5));
+    }
+
+    #[test]
+    fn ensure_fn_parse_version_works_as_expected_2() {
+        assert_eq!(parse_version("#playlist-maker-index v13\r\n"), Some(13));
+    }
+
+    #[test]
+    fn ensure_fn_parse_version_works_as_expected_3() {
+        assert_eq!(parse_version(r#""path","track","title""#), None);
+    }
+
+    #[test]
+    fn ensure_fn_stored_version_works_as_expected_1() {
+        let file = std::env::temp_dir().join("playlist-maker-stored-version-1.bin");
+        crate::index::binary::write(&file, "/music", &[]).unwrap();
+        let index = std::fs::read(&file).unwrap();
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(stored_version(&index[b"PLMKIDX".len()..]), Some(VERSION));
+        assert_eq!(stored_version(&[2, 0, 0, 0, 1, 2]), Some(2));
+        assert_eq!(stored_version(&[2, 0]), None);
+    }
+
+    #[test]
+    fn ensure_fn_migrate_works_as_expected_1() {
+        let headers = StringRecord::from(vec!["Path", "AlbumArtist", "date", "track"]);
+
+        let migrated = migrate(1, &headers);
+
+        assert_eq!(
+            migrated,
+            StringRecord::from(vec!["path", "album_artist", "year", "track"])
+        );
+    }
+
+    #[test]
+    fn ensure_fn_read_works_as_expected_1() {
+        let songs = read(Path::new("test-data/index.csv"), None);
+
+        assert_eq!(songs.len(), 17);
+    }
+
+    #[test]
+    fn ensure_fn_read_works_as_expected_2() {
+        let file = write_index(
+            "playlist-maker-read-2.csv",
+            r#"#playlist-maker-index v2
+"path","title","rating","modified"
+"test-data/songs/1.mp3","Passionfruit","5","1676678400"
+"#,
+        );
+
+        let songs = read(&file, None);
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(songs.len(), 1);
+        let song = songs.first().unwrap();
+        assert_eq!(song.path, "test-data/songs/1.mp3");
+        assert_eq!(song.title.as_deref(), Some("Passionfruit"));
+        assert_eq!(song.modified, Some(1676678400));
+        assert_eq!(song.artist, None);
+        assert_eq!(song.size, None);
+    }
+
+    #[test]
+    fn ensure_fn_read_works_as_expected_3() {
+        let file = write_index(
+            "playlist-maker-read-3.csv",
+            r#""path","albumartist","date"
+"test-data/songs/1.mp3","Drake","2017"
+"#,
+        );
+
+        let songs = read(&file, None);
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(songs.len(), 1);
+        let song = songs.first().unwrap();
+        assert_eq!(song.album_artist.as_deref(), Some("Drake"));
+        assert_eq!(song.year.as_deref(), Some("2017"));
+    }
+
+    #[test]
+    fn ensure_fn_read_works_as_expected_4() {
+        let file = write_index(
+            "playlist-maker-read-4.csv",
+            r#"#playlist-maker-index v3
+#library-root /home/a/Music
+"path","title"
+"Joji/Nectar/02. Modus.mp3","Modus"
+"#,
+        );
+
+        let songs = read(&file, None);
+        let rebased = read(&file, Some(Path::new("/mnt/phone/Music")));
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(
+            songs.first().unwrap().path,
+            "/home/a/Music/Joji/Nectar/02. Modus.mp3"
+        );
+        assert_eq!(
+            rebased.first().unwrap().path,
+            "/mnt/phone/Music/Joji/Nectar/02. Modus.mp3"
+        );
+    }
+
+    #[test]
+    fn ensure_fn_read_works_as_expected_5() {
+        let songs = read(
+            Path::new("test-data/index.csv"),
+            Some(Path::new("/mnt/phone/Music")),
+        );
+
+        assert!(songs.iter().any(|song| song.path
+            == "/mnt/phone/Music/Camila Cabello - Pop/2018 - Camila - Cd 1/04. Havana.mp3"));
+    }
+
+    #[test]
+    fn ensure_fn_relativize_works_as_expected_1() {
+        let songs = vec![
+            TagDetails {
+                path: "test-data/songs/1.mp3".to_string(),
+                ..Default::default()
+            },
+            TagDetails {
+                path: "test-data/songs/other/2.mp3".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let (root, songs) = relativize(&songs, None);
+
+        assert_eq!(
+            root,
+            absolute(Path::new("test-data/songs")).to_string_lossy()
+        );
+        assert_eq!(songs.first().unwrap().path, "1.mp3");
+        assert_eq!(songs.get(1).unwrap().path, "other/2.mp3");
+    }
+
+    #[test]
+    fn ensure_fn_relativize_works_as_expected_2() {
+        let songs = vec![TagDetails {
+            path: "/music/Joji/Nectar/01 Ew.mp3".to_string(),
+            ..Default::default()
+        }];
+
+        let (root, songs) = relativize(&songs, Some(Path::new("/music")));
+
+        assert_eq!(root, "/music");
+        assert_eq!(songs.first().unwrap().path, "Joji/Nectar/01 Ew.mp3");
+    }
+
+    #[test]
+    fn ensure_fn_read_root_works_as_expected_1() {
+        let csv = write_index(
+            "playlist-maker-read-root-1.csv",
+            &to_csv(Some(Path::new("/music")), &[]),
+        );
+        let binary = std::env::temp_dir().join("playlist-maker-read-root-1.inv");
+        crate::index::inverted::write(&binary, "/home/a/Music", &[]).unwrap();
+
+        let csv_root = read_root(&csv);
+        let binary_root = read_root(&binary);
+        std::fs::remove_file(&csv).unwrap();
+        std::fs::remove_file(&binary).unwrap();
+
+        assert_eq!(csv_root.as_deref(), Some("/music"));
+        assert_eq!(binary_root.as_deref(), Some("/home/a/Music"));
+        assert_eq!(read_root(Path::new("test-data/index.csv")), None);
+    }
+
+    #[test]
+    fn ensure_fn_format_works_as_expected_1() {
+        assert_eq!(format(Path::new("music/index.BIN")), IndexFormat::Binary);
+        assert_eq!(format(Path::new("music/index.inv")), IndexFormat::Inverted);
+        assert_eq!(format(Path::new("music/index.csv")), IndexFormat::Csv);
+        assert_eq!(format(Path::new("music/index")), IndexFormat::Csv);
+    }
+
+    #[test]
+    fn ensure_fn_read_works_as_expected_6() {
+        let file = write_index(
+            "playlist-maker-read-6.csv",
+            r#""path","title","modified"
+"test-data/songs/1.mp3","Passionfruit","yesterday"
+"test-data/songs/2.mp3","Modus","1676678400"
+"#,
+        );
+
+        let songs = read(&file, None);
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(songs.len(), 1);
+        assert_eq!(songs.first().unwrap().title.as_deref(), Some("Modus"));
+    }
+
+    #[test]
+    fn ensure_fn_read_csv_works_as_expected_1() {
+        let file = std::env::temp_dir().join("playlist-maker-read-csv-1.csv");
+        std::fs::write(&file, [0xff, 0xfe, b'\n']).unwrap();
+
+        let corrupt = read_csv(&file);
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(corrupt, None);
+        assert_eq!(read_csv(Path::new("test-data/missing.csv")), None);
+    }
+
+    #[test]
+    fn ensure_fn_to_csv_works_as_expected_1() {
+        let songs = vec![
+            TagDetails {
+                path: "/music/1.mp3".to_string(),
+                title: Some(String::from("Passionfruit")),
+                artist: Some(String::from("Drake")),
+                album: Some(String::from("More Life")),
+                album_artist: Some(String::from("Drake")),
+                year: Some(String::from("2017")),
+                genre: Some(String::from("Rap")),
+                disc: Some(String::from("1")),
+                track: Some(String::from("6")),
+                mb_artist_id: Some(String::from("9fff2f8a-21e6-47de-a2b8-7f449929d43f")),
+                modified: Some(1676678400),
+                size: Some(1166),
+                ..Default::default()
+            },
+            TagDetails {
+                path: "/music/2.mp3".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        assert_eq!(
+            to_csv(None, &songs),
+            r#"#playlist-maker-index v5
+#library-root /music
+"path","track","title","artist","album","album_artist","year","genre","disc","mb_recording_id","mb_release_id","mb_release_group_id","mb_artist_id","duration","modified","size"
+"1.mp3","6","Passionfruit","Drake","More Life","Drake","2017","Rap","1","","","","9fff2f8a-21e6-47de-a2b8-7f449929d43f","","1676678400","1166"
+"2.mp3","","","","","","","","","","","","","","","""#
+        );
+    }
+
+    #[test]
+    fn ensure_fn_to_csv_works_as_expected_2() {
+        let songs = vec![
+            TagDetails {
+                path: "/music/He said \"hi\", twice.mp3".to_string(),
+                title: Some(String::from("He said \"hi\"")),
+                artist: Some(String::from("Tyler, The Creator")),
+                album: Some(String::from("line one\nline two\r\n")),
+                genre: Some(String::from("\"\"")),
+                year: Some(String::from("")),
+                duration: Some(215),
+                ..Default::default()
+            },
+            TagDetails {
+                path: "/music/Beyoncé/#1 ☆.mp3".to_string(),
+                title: Some(String::from("#playlist-maker-index v9")),
+                album_artist: Some(String::from("  padded  ")),
+                ..Default::default()
+            },
+        ];
+        let file = write_index("playlist-maker-to-csv-2.csv", &to_csv(None, &songs));
+
+        let read = read(&file, None);
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(read.len(), 2);
+        let first = read.first().unwrap();
+        assert_eq!(first.path, "/music/He said \"hi\", twice.mp3");
+        assert_eq!(first.title.as_deref(), Some("He said \"hi\""));
+        assert_eq!(first.artist.as_deref(), Some("Tyler, The Creator"));
+        assert_eq!(first.album.as_deref(), Some("line one\nline two\r\n"));
+        assert_eq!(first.genre.as_deref(), Some("\"\""));
+        assert_eq!(first.duration, Some(215));
+        let second = read.get(1).unwrap();
+        assert_eq!(second.path, "/music/Beyoncé/#1 ☆.mp3");
+        assert_eq!(second.title.as_deref(), Some("#playlist-maker-index v9"));
+        assert_eq!(second.album_artist.as_deref(), Some("  padded  "));
+        assert_eq!(second.artist, None);
+    }
+
+    #[test]
+    fn ensure_fn_to_csv_works_as_expected_3() {
+        let file = write_index("playlist-maker-to-csv-3.csv", &to_csv(None, &[]));
+
+        let songs = read(&file, None);
+        std::fs::remove_file(&file).unwrap();
+
+        assert!(songs.is_empty());
+    }
+
+    fn write_index(name: &str, content: &str) -> PathBuf {
+        let file = std::env::temp_dir().join(name);
+        std::fs::write(&file, content).unwrap();
+        file
+    }
+}
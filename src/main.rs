@@ -4,22 +4,38 @@ extern crate pest_derive;
 #[macro_use]
 extern crate serde_derive;
 
+mod index;
 mod playlist;
 mod query;
 mod tag;
 mod utils;
 
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::exit;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use rayon::prelude::*;
 
+use crate::index::inverted::Postings;
 use crate::playlist::Playlist;
+use crate::query::batch;
+use crate::query::library::QueryLibrary;
 use crate::query::processor;
+use crate::query::processor::QueryType;
 use crate::tag::details::TagDetails;
-use crate::utils::fs::{get_playlists, get_songs};
+use crate::tag::writer;
+use crate::utils::fs::{
+    get_playlists, get_songs_with_postings, library_root, relocate_indexes, relocate_playlists,
+    update_songs,
+};
 use crate::utils::iter::AlmostEqualDivision;
-use crate::utils::printer::{Output, Printer};
+use crate::utils::moves;
+use crate::utils::moves::Move;
+use crate::utils::paths::normalize;
+use crate::utils::printer::{Format, Output, PathStyle, Printer};
+use crate::utils::template::Template;
 
 /// Create playlists using a query language
 #[derive(Parser, Debug)]
@@ -31,27 +47,126 @@ pub struct Cli {
     ///File to write the query results to (if not specified send to stdout)
     #[arg(short, long)]
     output: Option<PathBuf>,
-    ///Path to m3u playlist to be used in the query (can be repeated if needed)
+    ///Path or glob pattern of m3u, m3u8, pls or xspf playlists to be used in the query (can be repeated if needed)
     #[arg(short, long)]
     playlist: Vec<PathBuf>,
+    ///Directory with playlists to be used in the query, named by their path relative to it (can be repeated if needed)
+    #[arg(long)]
+    playlist_dir: Vec<PathBuf>,
+    ///Compare the paths of playlist songs ignoring their case (for case-insensitive filesystems)
+    #[arg(long)]
+    ignore_path_case: bool,
     ///Query to execute
+    #[arg(short, long, required_unless_present_any = ["convert", "batch"])]
+    query: Option<String>,
+    ///File with one `<name> = <expression>` per line, used in queries with `Query("<name>")`
     #[arg(short, long)]
-    query: String,
+    library: Option<PathBuf>,
+    ///File with one `<name> = <query>` per line, each query written to `<name>.<format>` in the output directory
+    #[arg(short, long, conflicts_with_all = ["query", "convert", "update_index"])]
+    batch: Option<PathBuf>,
+    ///Reuse the songs in the output index of an `Index` query, only reading the tags of new or modified files
+    #[arg(short, long, requires = "output")]
+    update_index: bool,
+    ///Index to convert to the output index (csv, binary or inverted, according to the extensions used)
+    #[arg(short, long, requires = "output", conflicts_with = "query")]
+    convert: Option<PathBuf>,
+    ///Library root to load indexed songs from, instead of the one recorded in the index
+    #[arg(short, long)]
+    music_root: Option<PathBuf>,
+    ///Write the paths of the songs as absolute or relative to the output file
+    #[arg(long, value_enum, conflicts_with = "path_prefix")]
+    path_style: Option<PathMode>,
+    ///Write the paths of the songs relative to their library root, under this prefix
+    #[arg(long)]
+    path_prefix: Option<PathBuf>,
+    ///Layout of the playlist (csv, json and ndjson also for indexes and stats, shown as a table otherwise)
+    #[arg(short, long, value_enum, default_value_t = PlaylistFormat::Plain)]
+    format: PlaylistFormat,
+    ///Name of the playlist, written by extended M3U and XSPF playlists
+    #[arg(short, long)]
+    title: Option<String>,
+    ///Write one line per song with this template instead, e.g. `{track:02} - {artist} - {title|upper} ({year|Unknown})`
+    #[arg(long, conflicts_with = "format")]
+    template: Option<String>,
+    ///Show the tag changes of `Set` queries and the moves of `Move` queries without making them
+    #[arg(long)]
+    dry_run: bool,
+    ///Csv file the moves of `Move` queries are appended to, to be able to undo them
+    #[arg(long, default_value = "pl-mker-undo.csv")]
+    undo_log: PathBuf,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+enum PathMode {
+    Absolute,
+    Relative,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+enum PlaylistFormat {
+    Plain,
+    M3uExt,
+    Pls,
+    Xspf,
+    Json,
+    Ndjson,
+    Csv,
 }
 
 fn main() {
     let cli = build_cli();
 
+    if let Some(batch) = cli.batch.to_owned() {
+        run_batch(cli, &batch);
+        return;
+    }
+
     let printer = build_printer(&cli);
+    check_layout(&printer);
+    check_update_index(&printer, cli.update_index);
+
+    let outcome = match (cli.convert.to_owned(), cli.query.to_owned()) {
+        (Some(index), _) => index::read(&index, cli.music_root.as_deref()),
+        (None, Some(query)) => {
+            let library = get_library(cli.library.as_deref());
+            let query = resolve_query(&library, &query);
+            let music_root = cli.music_root.as_deref();
+            let (songs, postings) = match (cli.update_index, cli.output.as_deref()) {
+                (true, Some(index)) => {
+                    (update_songs(cli.input.to_owned(), music_root, index), None)
+                }
+                _ => get_songs_with_postings(cli.input.to_owned(), music_root),
+            };
 
-    let outcome = filter_songs(
-        cli.query,
-        get_playlists(cli.playlist),
-        get_songs(cli.input),
-        num_cpus::get(),
-    );
+            filter_songs(
+                query,
+                get_playlists(
+                    cli.playlist.to_owned(),
+                    cli.playlist_dir.to_owned(),
+                    cli.ignore_path_case,
+                    &library,
+                ),
+                songs,
+                postings,
+                num_cpus::get(),
+            )
+        }
+        (None, None) => unreachable!(),
+    };
 
-    printer.print(&outcome);
+    let written = write_tags(&printer.print_type, &outcome, cli.dry_run);
+    let planned = printer.plan(&outcome);
+    if printer.print(&outcome, &planned).is_none() {
+        match &printer.output {
+            Output::File(file) => println!("could not write `{}`!", file.display()),
+            Output::Terminal => println!("could not write the songs!"),
+        }
+        exit(2);
+    }
+    if written.and(move_songs(&planned, &cli)).is_none() {
+        exit(2);
+    }
 }
 
 fn build_cli() -> Cli {
@@ -64,39 +179,295 @@ fn build_printer(cli: &Cli) -> Printer {
             None => Output::Terminal,
             Some(file) => Output::File(file.into()),
         },
-        print_type: processor::get_type(&cli.query),
+        print_type: cli
+            .query
+            .as_deref()
+            .map_or(QueryType::Index, processor::get_type),
+        path_style: match (&cli.path_style, &cli.path_prefix) {
+            (_, Some(prefix)) => PathStyle::Prefix(prefix.to_owned()),
+            (Some(PathMode::Absolute), None) => PathStyle::Absolute,
+            (Some(PathMode::Relative), None) => PathStyle::Relative,
+            (None, None) => PathStyle::AsIs,
+        },
+        format: match (&cli.template, &cli.format) {
+            (Some(template), _) => {
+                Format::Template(Template::parse(template).unwrap_or_else(|error| {
+                    println!("{}", error);
+                    exit(2);
+                }))
+            }
+            (None, PlaylistFormat::Plain) => Format::Plain,
+            (None, PlaylistFormat::M3uExt) => Format::M3uExt,
+            (None, PlaylistFormat::Pls) => Format::Pls,
+            (None, PlaylistFormat::Xspf) => Format::Xspf,
+            (None, PlaylistFormat::Json) => Format::Json,
+            (None, PlaylistFormat::Ndjson) => Format::Ndjson,
+            (None, PlaylistFormat::Csv) => Format::Csv,
+        },
+        title: cli.title.to_owned(),
+        library_root: library_root(
+            &cli.convert
+                .to_owned()
+                .map_or(cli.input.to_owned(), |index| vec![index]),
+            cli.music_root.as_deref(),
+        ),
+    }
+}
+
+/// Runs every query of the batch over the same songs, loaded only once, writing each outcome to a
+/// file named after the query in the output directory (the current one if not specified)
+fn run_batch(cli: Cli, batch: &Path) {
+    let dir = cli.output.to_owned().unwrap_or_default();
+    let library = get_library(cli.library.as_deref());
+    let printers = batch::read(batch)
+        .into_iter()
+        .map(|named| {
+            let printer = Printer {
+                print_type: processor::get_type(&named.query),
+                title: cli.title.to_owned().or(Some(named.name.to_owned())),
+                ..build_printer(&cli)
+            };
+            let file = dir.join(format!("{}.{}", named.name, printer.extension()));
+            let printer = Printer {
+                output: Output::File(file),
+                ..printer
+            };
+            check_layout(&printer);
+            (resolve_query(&library, &named.query), printer)
+        })
+        .collect::<Vec<(String, Printer)>>();
+
+    let (songs, postings) =
+        get_songs_with_postings(cli.input.to_owned(), cli.music_root.as_deref());
+    let load_playlists = || {
+        get_playlists(
+            cli.playlist.to_owned(),
+            cli.playlist_dir.to_owned(),
+            cli.ignore_path_case,
+            &library,
+        )
+    };
+    let mut playlists = load_playlists();
+    let mut songs = Songs::new(songs, postings, num_cpus::get());
+
+    for (query, printer) in printers {
+        let outcome = filter_loaded(&query, &playlists, &songs);
+        if write_tags(&printer.print_type, &outcome, cli.dry_run).is_none() {
+            exit(2);
+        }
+        let planned = printer.plan(&outcome);
+        if let Output::File(file) = &printer.output {
+            if file
+                .parent()
+                .is_some_and(|dir| fs::create_dir_all(dir).is_err())
+                || printer.print(&outcome, &planned).is_none()
+            {
+                println!("could not write `{}`!", file.display());
+                exit(2);
+            }
+        }
+        // the following queries see the moved songs, and the playlists rewritten for them, at
+        // their new paths
+        match move_songs(&planned, &cli) {
+            Some(moved) if !moved.is_empty() => {
+                songs.relocate(&moved);
+                playlists = load_playlists();
+            }
+            Some(_) => {}
+            None => exit(2),
+        }
+    }
+}
+
+/// Writes the tags asked by a `Set` query to the songs, unless it's a dry run, telling which songs
+/// could not be written
+fn write_tags(print_type: &QueryType, songs: &[TagDetails], dry_run: bool) -> Option<()> {
+    let failed = match (print_type, dry_run) {
+        (QueryType::Set(assignments), false) => writer::write(&writer::changes(songs, assignments)),
+        _ => vec![],
+    };
+    failed
+        .iter()
+        .for_each(|path| println!("could not write the tags of `{}`!", path));
+    failed.is_empty().then_some(())
+}
+
+/// Relative layouts of `Move` queries are resolved against the library root, which is not known
+/// when an input index has no recorded root
+fn check_layout(printer: &Printer) {
+    if let QueryType::Move(layout) = &printer.print_type {
+        if printer.library_root.is_none() && moves::is_relative(layout) {
+            println!("the library root of the songs is not known, give it with `--music-root` or use an absolute layout!");
+            exit(2);
+        }
+    }
+}
+
+/// Only indexes can be updated, the output of other queries can't be read back as songs
+fn check_update_index(printer: &Printer, update_index: bool) {
+    if update_index && printer.print_type != QueryType::Index {
+        println!("`--update-index` can only be used with `Index` queries!");
+        exit(2);
     }
 }
 
+/// Makes the moves planned for a `Move` query (see `Printer::plan`), unless it's a dry run,
+/// appending the moves made to the undo log and updating the playlists and input indexes
+/// referencing the songs moved. Returns the new paths of the moved songs by their old path, `None`
+/// when something could not be written
+fn move_songs(planned: &[Move], cli: &Cli) -> Option<HashMap<String, String>> {
+    if cli.dry_run {
+        return Some(HashMap::new());
+    }
+    let (moved, failed) = moves::apply(planned);
+    failed
+        .iter()
+        .for_each(|path| println!("could not move `{}`!", path));
+    if moved.is_empty() {
+        return failed.is_empty().then(HashMap::new);
+    }
+
+    let logged = moves::log(&cli.undo_log, &moved);
+    if logged.is_none() {
+        println!("could not write the undo log `{}`!", cli.undo_log.display());
+    }
+    let key = |path: &Path| {
+        let path = normalize(path).to_string_lossy().to_string();
+        match cli.ignore_path_case {
+            true => path.to_lowercase(),
+            false => path,
+        }
+    };
+    let destinations = moved
+        .iter()
+        .map(|song| (key(Path::new(&song.from)), PathBuf::from(&song.to)))
+        .collect::<HashMap<String, PathBuf>>();
+    let destination = |entry: &str| destinations.get(&key(Path::new(entry))).cloned();
+    let unchanged = relocate_playlists(&cli.playlist, &cli.playlist_dir, &destination);
+    unchanged
+        .iter()
+        .for_each(|playlist| println!("could not update playlist `{}`!", playlist.display()));
+    let stale = relocate_indexes(&cli.input, cli.music_root.as_deref(), &destination);
+    stale
+        .iter()
+        .for_each(|index| println!("could not update index `{}`!", index.display()));
+    (failed.is_empty() && logged.is_some() && unchanged.is_empty() && stale.is_empty()).then(|| {
+        moved
+            .iter()
+            .map(|song| (song.from.to_owned(), song.to.to_owned()))
+            .collect()
+    })
+}
+
+fn get_library(file: Option<&Path>) -> QueryLibrary {
+    file.map_or_else(QueryLibrary::default, QueryLibrary::read)
+}
+
+fn resolve_query(library: &QueryLibrary, query: &str) -> String {
+    library.resolve(query).unwrap_or_else(|error| {
+        println!("{}", error);
+        exit(2);
+    })
+}
+
 fn filter_songs(
     query: String,
     playlists: Vec<Playlist>,
     songs: Vec<TagDetails>,
+    postings: Option<Postings>,
     num_cpus: usize,
 ) -> Vec<TagDetails> {
-    songs
-        .divide_collection_by(num_cpus)
-        .par_iter()
-        .filter_map(|songs| processor::process(songs, &playlists, &query))
-        .flatten()
-        .collect::<Vec<TagDetails>>()
+    filter_loaded(&query, &playlists, &Songs::new(songs, postings, num_cpus))
+}
+
+/// Songs queries run over: divided in chunks filtered in parallel or, when read from an inverted
+/// index, kept in the order of its postings
+enum Songs {
+    Chunks(Vec<Vec<TagDetails>>),
+    Indexed(Vec<TagDetails>, Postings),
+}
+
+impl Songs {
+    fn new(songs: Vec<TagDetails>, postings: Option<Postings>, num_cpus: usize) -> Self {
+        match postings {
+            Some(postings) => Songs::Indexed(songs, postings),
+            None => Songs::Chunks(songs.divide_collection_by(num_cpus)),
+        }
+    }
+
+    /// Songs moved by a query seen at their new path by the following ones, `moved` giving the new
+    /// path of the songs by their old one. Postings don't have paths, so they are still valid
+    fn relocate(&mut self, moved: &HashMap<String, String>) {
+        let relocate = |song: &mut TagDetails| {
+            if let Some(to) = moved.get(&song.path) {
+                song.path = to.to_owned();
+            }
+        };
+        match self {
+            Songs::Chunks(chunks) => chunks.iter_mut().flatten().for_each(relocate),
+            Songs::Indexed(songs, _) => songs.iter_mut().for_each(relocate),
+        }
+    }
+}
+
+fn filter_loaded(query: &str, playlists: &[Playlist], songs: &Songs) -> Vec<TagDetails> {
+    let playlists = processor::select_saved(playlists, |playlists, query| {
+        select(query, playlists, songs)
+    });
+    processor::check_positions(&playlists, query);
+    let outcome = select(query, &playlists, songs);
+    let mut outcome = processor::distinct(outcome, query);
+    processor::sort(&mut outcome, &playlists, query);
+    outcome
+}
+
+/// Songs matching the query expression, in any order
+fn select(query: &str, playlists: &[Playlist], songs: &Songs) -> Vec<TagDetails> {
+    match songs {
+        Songs::Chunks(chunks) => chunks
+            .par_iter()
+            .filter_map(|songs| processor::process(songs, playlists, query))
+            .flatten()
+            .collect::<Vec<TagDetails>>(),
+        Songs::Indexed(songs, postings) => {
+            processor::process_indexed(songs, playlists, postings, query).unwrap_or_default()
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::path::PathBuf;
-    use crate::{build_printer, Cli, filter_songs};
+    use crate::query::processor;
     use crate::query::processor::QueryType;
     use crate::tag::details::TagDetails;
-    use crate::utils::printer::Output;
+    use crate::utils::printer::{Format, Output, PathStyle};
+    use crate::utils::template::Template;
+    use crate::{
+        build_printer, filter_songs, run_batch, write_tags, Cli, PathMode, PlaylistFormat,
+    };
+    use std::path::PathBuf;
 
     #[test]
     fn ensure_fn_build_printer_works_as_expected_1() {
         let cli = Cli {
-            query: "Play(Artist('a'))".to_string(),
+            query: Some("Play(Artist('a'))".to_string()),
+            batch: None,
+            library: None,
             input: vec![PathBuf::from("ii")],
             output: None,
             playlist: vec![],
+            playlist_dir: vec![],
+            ignore_path_case: false,
+            update_index: false,
+            convert: None,
+            music_root: None,
+            path_style: None,
+            path_prefix: None,
+            format: PlaylistFormat::Plain,
+            title: None,
+            template: None,
+            dry_run: false,
+            undo_log: PathBuf::from("pl-mker-undo.csv"),
         };
         let printer = build_printer(&cli);
 
@@ -107,10 +478,24 @@ mod tests {
     #[test]
     fn ensure_fn_build_printer_works_as_expected_2() {
         let cli = Cli {
-            query: "Index(Artist('a'))".to_string(),
+            query: Some("Index(Artist('a'))".to_string()),
+            batch: None,
+            library: None,
             input: vec![PathBuf::from("ii")],
             output: Some(PathBuf::from("oo")),
             playlist: vec![],
+            playlist_dir: vec![],
+            ignore_path_case: false,
+            update_index: false,
+            convert: None,
+            music_root: None,
+            path_style: None,
+            path_prefix: None,
+            format: PlaylistFormat::Plain,
+            title: None,
+            template: None,
+            dry_run: false,
+            undo_log: PathBuf::from("pl-mker-undo.csv"),
         };
         let printer = build_printer(&cli);
 
@@ -118,6 +503,162 @@ mod tests {
         assert_eq!(printer.print_type, QueryType::Index);
     }
 
+    #[test]
+    fn ensure_fn_build_printer_works_as_expected_3() {
+        let cli = Cli {
+            query: None,
+            batch: None,
+            library: None,
+            input: vec![],
+            output: Some(PathBuf::from("index.bin")),
+            playlist: vec![],
+            playlist_dir: vec![],
+            ignore_path_case: false,
+            update_index: false,
+            convert: Some(PathBuf::from("index.csv")),
+            music_root: None,
+            path_style: None,
+            path_prefix: None,
+            format: PlaylistFormat::Plain,
+            title: None,
+            template: None,
+            dry_run: false,
+            undo_log: PathBuf::from("pl-mker-undo.csv"),
+        };
+        let printer = build_printer(&cli);
+
+        assert_eq!(printer.output, Output::File(PathBuf::from("index.bin")));
+        assert_eq!(printer.print_type, QueryType::Index);
+    }
+
+    #[test]
+    fn ensure_fn_build_printer_works_as_expected_4() {
+        let cli = Cli {
+            query: Some("Play(Artist('a'))".to_string()),
+            batch: None,
+            library: None,
+            input: vec![PathBuf::from("ii")],
+            output: Some(PathBuf::from("oo.m3u")),
+            playlist: vec![],
+            playlist_dir: vec![],
+            ignore_path_case: false,
+            update_index: false,
+            convert: None,
+            music_root: None,
+            path_style: Some(PathMode::Relative),
+            path_prefix: None,
+            format: PlaylistFormat::Plain,
+            title: None,
+            template: None,
+            dry_run: false,
+            undo_log: PathBuf::from("pl-mker-undo.csv"),
+        };
+        let printer = build_printer(&cli);
+
+        assert_eq!(printer.path_style, PathStyle::Relative);
+    }
+
+    #[test]
+    fn ensure_fn_build_printer_works_as_expected_5() {
+        let cli = Cli {
+            query: Some("Play(Artist('a'))".to_string()),
+            batch: None,
+            library: None,
+            input: vec![PathBuf::from("ii")],
+            output: Some(PathBuf::from("oo.m3u")),
+            playlist: vec![],
+            playlist_dir: vec![],
+            ignore_path_case: false,
+            update_index: false,
+            convert: None,
+            music_root: None,
+            path_style: None,
+            path_prefix: None,
+            format: PlaylistFormat::M3uExt,
+            title: Some("Road Trip".to_string()),
+            template: None,
+            dry_run: false,
+            undo_log: PathBuf::from("pl-mker-undo.csv"),
+        };
+        let printer = build_printer(&cli);
+
+        assert_eq!(printer.format, Format::M3uExt);
+        assert_eq!(printer.title.as_deref(), Some("Road Trip"));
+    }
+
+    #[test]
+    fn ensure_fn_build_printer_works_as_expected_6() {
+        let cli = Cli {
+            query: Some("Play(Artist(\"Drake\"))".to_string()),
+            batch: None,
+            library: None,
+            input: vec![],
+            output: None,
+            playlist: vec![],
+            playlist_dir: vec![],
+            ignore_path_case: false,
+            update_index: false,
+            convert: None,
+            music_root: None,
+            path_style: None,
+            path_prefix: None,
+            format: PlaylistFormat::Plain,
+            title: None,
+            template: Some("{track:02} - {title}".to_string()),
+            dry_run: false,
+            undo_log: PathBuf::from("pl-mker-undo.csv"),
+        };
+        let printer = build_printer(&cli);
+
+        assert_eq!(
+            printer.format,
+            Format::Template(Template::parse("{track:02} - {title}").unwrap())
+        );
+        assert_eq!(printer.extension(), "txt");
+    }
+
+    #[test]
+    fn ensure_fn_run_batch_works_as_expected_1() {
+        let dir = std::env::temp_dir().join("playlist-maker-run-batch-1");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let batch = dir.join("queries");
+        std::fs::write(
+            &batch,
+            "drake = Play(Artist(\"Drake\"))\nnone/nobody = Play(Artist(\"Nobody\"))\n",
+        )
+        .unwrap();
+        let cli = Cli {
+            query: None,
+            batch: Some(batch.clone()),
+            library: None,
+            input: vec![PathBuf::from("test-data/songs")],
+            output: Some(dir.clone()),
+            playlist: vec![],
+            playlist_dir: vec![],
+            ignore_path_case: false,
+            update_index: false,
+            convert: None,
+            music_root: None,
+            path_style: None,
+            path_prefix: None,
+            format: PlaylistFormat::M3uExt,
+            title: None,
+            template: None,
+            dry_run: false,
+            undo_log: PathBuf::from("pl-mker-undo.csv"),
+        };
+
+        run_batch(cli, &batch);
+        let drake = std::fs::read_to_string(dir.join("drake.m3u")).unwrap();
+        let nobody = std::fs::read_to_string(dir.join("none/nobody.m3u")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(drake.starts_with("#EXTM3U\n#PLAYLIST:drake\n"));
+        assert_eq!(drake.matches("#EXTINF").count(), 2);
+        assert_eq!(nobody, "#EXTM3U\n#PLAYLIST:none/nobody\n");
+    }
+
     #[test]
     fn ensure_fn_process_works_as_expected_1() {
         let songs = default_songs();
@@ -127,6 +668,7 @@ mod tests {
             r#"Play(Album("Black"))"#.to_string(),
             playlists,
             songs,
+            None,
             3,
         );
 
@@ -137,6 +679,26 @@ mod tests {
             .is_some());
     }
 
+    #[test]
+    fn ensure_fn_write_tags_works_as_expected_1() {
+        let copy = std::env::temp_dir().join("playlist-maker-write-tags.mp3");
+        std::fs::copy("test-data/songs/id3v2.4.mp3", &copy).unwrap();
+        let songs = vec![TagDetails::try_from(&copy).unwrap()];
+        let set = processor::get_type(r#"Set(Genre("Rap"), Genre = "Jazz")"#);
+
+        let dry_run = write_tags(&set, &songs, true);
+        let unchanged = TagDetails::try_from(&copy).unwrap();
+        let written = write_tags(&set, &songs, false);
+        let changed = TagDetails::try_from(&copy).unwrap();
+        std::fs::remove_file(&copy).unwrap();
+
+        assert_eq!(dry_run, Some(()));
+        assert_eq!(unchanged.genre.as_deref(), Some("Rap"));
+        assert_eq!(written, Some(()));
+        assert_eq!(changed.genre.as_deref(), Some("Jazz"));
+        assert_eq!(write_tags(&QueryType::Play, &songs, false), Some(()));
+    }
+
     fn default_songs() -> Vec<TagDetails> {
         let info1 = TagDetails {
             path: "test-data/songs/1.mp3".to_string(),
@@ -0,0 +1,118 @@
+use crate::playlist;
+use crate::playlist::{lines_with_breaks, resolve};
+use crate::tag::details::TagDetails;
+use std::path::{Path, PathBuf};
+
+/// Paths of the songs in a M3U playlist, `dir` being the directory of the playlist: comments and
+/// directives (e.g. `#EXTINF`) are skipped and entries are resolved as in `resolve`
+pub fn parse(content: &str, dir: &Path) -> Vec<String> {
+    content
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .map(|line| line.trim_end_matches('\r').trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| resolve(line, dir))
+        .collect()
+}
+
+/// M3U playlist with the entries of moved songs replaced as in `playlist::relocate`, everything
+/// else written as it was
+pub fn relocate(content: &str, dir: &Path, moved: &impl Fn(&str) -> Option<PathBuf>) -> String {
+    lines_with_breaks(content)
+        .map(|(line, end)| {
+            let entry = line.trim_start_matches('\u{feff}').trim();
+            match entry.is_empty() || entry.starts_with('#') {
+                true => None,
+                false => playlist::relocate(entry, dir, moved),
+            }
+            .map_or_else(
+                || format!("{}{}", line, end),
+                |path| format!("{}{}{}", &line[..line.find(entry).unwrap_or(0)], path, end),
+            )
+        })
+        .collect()
+}
+
+/// M3U8 playlists are always UTF-8, plain M3U playlists are read as UTF-8 when valid and as
+/// Latin-1 (the encoding of the original format) otherwise
+pub fn decode(bytes: Vec<u8>, utf8: bool) -> String {
+    match String::from_utf8(bytes) {
+        Ok(content) => content,
+        Err(error) if utf8 => String::from_utf8_lossy(error.as_bytes()).to_string(),
+        Err(error) => error.as_bytes().iter().map(|&byte| byte as char).collect(),
+    }
+}
+
+/// Extended M3U playlist, `paths` being the paths of `songs` as they should be written
+pub fn format(title: Option<&str>, songs: &[TagDetails], paths: Vec<String>) -> String {
+    let mut lines = vec![String::from("#EXTM3U")];
+    if let Some(title) = title {
+        lines.push(format!("#PLAYLIST:{}", title));
+    }
+    for (song, path) in songs.iter().zip(paths) {
+        lines.push(format!(
+            "#EXTINF:{},{}",
+            song.duration.map_or(-1, |e| e as i64),
+            song.display_name()
+        ));
+        lines.push(path);
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::playlist::m3u::{decode, parse, relocate};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn ensure_fn_parse_works_as_expected_1() {
+        let content =
+            "#EXTM3U\r\n#EXTINF:215,Drake - Passionfruit\r\nsongs/1.mp3\r\n\r\n/music/2.mp3\r\n";
+
+        let songs = parse(content, Path::new("test-data"));
+
+        assert_eq!(songs, vec!["test-data/songs/1.mp3", "/music/2.mp3"]);
+    }
+
+    #[test]
+    fn ensure_fn_parse_works_as_expected_2() {
+        let content = "\u{feff}test-data/songs/1.mp3\n# a comment\ntest-data/songs/2.mp3";
+
+        let songs = parse(content, Path::new(""));
+
+        assert_eq!(
+            songs,
+            vec!["test-data/songs/1.mp3", "test-data/songs/2.mp3"]
+        );
+    }
+
+    #[test]
+    fn ensure_fn_decode_works_as_expected_1() {
+        let bytes = "Beyoncé.mp3".as_bytes().to_vec();
+
+        assert_eq!(decode(bytes.clone(), true), "Beyoncé.mp3");
+        assert_eq!(decode(bytes, false), "Beyoncé.mp3");
+    }
+
+    #[test]
+    fn ensure_fn_decode_works_as_expected_2() {
+        let bytes = b"Beyonc\xe9.mp3".to_vec();
+
+        assert_eq!(decode(bytes.clone(), true), "Beyonc\u{fffd}.mp3");
+        assert_eq!(decode(bytes, false), "Beyoncé.mp3");
+    }
+
+    #[test]
+    fn ensure_fn_relocate_works_as_expected_1() {
+        let content = "#EXTM3U\r\n#EXTINF:207,Joji - Ew\r\n Ew.mp3\r\n/music/Run.mp3\r\n";
+        let moved = |path: &str| {
+            (path == "/music/Ew.mp3").then(|| PathBuf::from("/music/Joji/Nectar/01 Ew.mp3"))
+        };
+
+        assert_eq!(
+            relocate(content, Path::new("/music"), &moved),
+            "#EXTM3U\r\n#EXTINF:207,Joji - Ew\r\n Joji/Nectar/01 Ew.mp3\r\n/music/Run.mp3\r\n"
+        );
+    }
+}
@@ -1,43 +1,176 @@
+pub mod m3u;
+pub mod pls;
+pub mod xspf;
+
 use crate::tag::details::TagDetails;
+use crate::utils::paths::{absolute, canonical, normalize, relative_to};
+use percent_encoding::percent_decode_str;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Prefix of the playlist entries that reference a saved query instead of a song
+pub const QUERY_PREFIX: &str = "@query:";
 
 #[derive(Clone)]
 pub struct Playlist {
     pub name: String,
     pub songs: Vec<String>,
+    /// Saved queries (already resolved) whose songs are also part of the playlist, they have no
+    /// position
+    pub queries: Vec<String>,
+    /// Paths of the songs selected by `queries`, see `processor::select_saved`
+    pub saved: HashSet<String>,
+    /// Position (starting at 1) of the songs by their normalized and canonical forms, to match
+    /// songs found through other paths
+    positions: HashMap<String, usize>,
+    /// Directory relative paths of songs are resolved against, looked up once instead of for every
+    /// song
+    cwd: PathBuf,
+    /// Compare paths ignoring their case, for case-insensitive filesystems
+    ignore_case: bool,
 }
 
 impl Playlist {
+    pub fn new(name: String, songs: Vec<String>, ignore_case: bool) -> Self {
+        let mut playlist = Playlist {
+            name,
+            songs: Vec::new(),
+            queries: Vec::new(),
+            saved: HashSet::new(),
+            positions: HashMap::new(),
+            cwd: std::env::current_dir().unwrap_or_default(),
+            ignore_case,
+        };
+        for (position, song) in songs.iter().enumerate() {
+            let key = playlist.key(&normalize(Path::new(song)).to_string_lossy());
+            playlist.positions.entry(key).or_insert(position + 1);
+            if let Some(path) = canonical(Path::new(song)) {
+                let key = playlist.key(&path.to_string_lossy());
+                playlist.positions.entry(key).or_insert(position + 1);
+            }
+        }
+        playlist.songs = songs;
+        playlist
+    }
+
+    /// Songs of the playlist, the ones selected by its saved queries included
     pub fn filter(&self, vec: &[TagDetails]) -> Vec<TagDetails> {
-        return vec
-            .iter()
-            .filter(|song| self.songs.contains(&song.path))
+        vec.iter()
+            .filter(|song| self.contains(&song.path))
+            .map(|song| song.to_owned())
+            .collect::<Vec<TagDetails>>()
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.saved.contains(path) || self.position(path).is_some()
+    }
+
+    /// Songs of the playlist whose position (starting at 1) matches `predicate`
+    pub fn filter_by_position(
+        &self,
+        vec: &[TagDetails],
+        predicate: impl Fn(usize) -> bool,
+    ) -> Vec<TagDetails> {
+        vec.iter()
+            .filter(|song| self.position(&song.path).is_some_and(&predicate))
             .map(|song| song.to_owned())
-            .collect::<Vec<TagDetails>>();
+            .collect::<Vec<TagDetails>>()
+    }
+
+    /// Position (starting at 1) of the first entry of the song in the playlist. Songs are compared
+    /// by their canonical path, like entries, so a song found through a symlink matches the entry
+    /// with its real path and the other way around. Songs that can't be resolved (e.g. missing
+    /// files) are compared by their normalized path
+    pub fn position(&self, path: &str) -> Option<usize> {
+        let path = self.cwd.join(path);
+        let path = canonical(&path).unwrap_or_else(|| normalize(&path));
+        self.positions
+            .get(&self.key(&path.to_string_lossy()))
+            .copied()
+    }
+
+    fn key(&self, path: &str) -> String {
+        if self.ignore_case {
+            path.to_lowercase()
+        } else {
+            path.to_string()
+        }
+    }
+}
+
+/// Path of a playlist entry: `file://` URIs are decoded, other URIs (e.g. streams) are skipped and
+/// relative entries are resolved against `dir`, the directory of the playlist, unless only the
+/// folder of the entry as written exists (playlists written relative to the working directory).
+/// Saved query references are kept as they are
+pub fn resolve(entry: &str, dir: &Path) -> Option<String> {
+    if entry.starts_with(QUERY_PREFIX) {
+        return Some(entry.to_string());
+    }
+    match entry.split_once("://") {
+        Some((scheme, path)) if scheme.eq_ignore_ascii_case("file") => {
+            // file URIs can have a host, usually empty or localhost
+            let path = &path[path.find('/')?..];
+            Some(percent_decode_str(path).decode_utf8_lossy().to_string())
+        }
+        Some(_) => None,
+        None => {
+            let path = dir.join(entry);
+            let has_folder = |path: &Path| path.parent().is_some_and(Path::is_dir);
+            match !has_folder(&path) && has_folder(Path::new(entry)) {
+                true => Some(entry.to_string()),
+                false => Some(path.to_string_lossy().to_string()),
+            }
+        }
     }
 }
 
+/// Entry of a song that was moved, written like the entry was (as a `file://` URI, absolute or
+/// relative to `dir`), `moved` giving the new path of a song by its resolved path. `None` for
+/// entries of songs that were not moved
+pub fn relocate(
+    entry: &str,
+    dir: &Path,
+    moved: &impl Fn(&str) -> Option<PathBuf>,
+) -> Option<String> {
+    let to = moved(&resolve(entry, dir).filter(|path| !path.starts_with(QUERY_PREFIX))?)?;
+    Some(if entry.contains("://") {
+        xspf::location(&absolute(&to).to_string_lossy())
+    } else if Path::new(entry).is_absolute() {
+        absolute(&to).to_string_lossy().to_string()
+    } else {
+        relative_to(&to, dir).to_string_lossy().to_string()
+    })
+}
+
+/// Lines of a text with their line break, so the ones not changed are written back as they were
+fn lines_with_breaks(content: &str) -> impl Iterator<Item = (&str, &str)> {
+    content.split_inclusive('\n').map(|line| {
+        let text = line.trim_end_matches(['\n', '\r']);
+        (text, &line[text.len()..])
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::playlist::Playlist;
+    use crate::playlist::{relocate, resolve, Playlist};
     use crate::tag::details::TagDetails;
+    use std::path::{Path, PathBuf};
 
     #[test]
     fn empty_playlist_removes_all_tag_details() {
-        let filtered = &Playlist {
-            name: "test".to_string(),
-            songs: vec![],
-        }
-        .filter(default_songs().as_slice());
+        let filtered =
+            &Playlist::new("test".to_string(), vec![], false).filter(default_songs().as_slice());
 
         assert_eq!(0, filtered.len())
     }
 
     #[test]
     fn basic_playlist_removes_expected_tag_details_1() {
-        let filtered = &Playlist {
-            name: "test".to_string(),
-            songs: vec!["test-data/songs/1.mp3".to_string()],
-        }
+        let filtered = &Playlist::new(
+            "test".to_string(),
+            vec!["test-data/songs/1.mp3".to_string()],
+            false,
+        )
         .filter(default_songs().as_slice());
 
         assert_eq!(1, filtered.len())
@@ -45,13 +178,14 @@ mod tests {
 
     #[test]
     fn basic_playlist_removes_expected_tag_details_2() {
-        let filtered = &Playlist {
-            name: "test".to_string(),
-            songs: vec![
+        let filtered = &Playlist::new(
+            "test".to_string(),
+            vec![
                 "test-data/songs/3.mp3".to_string(),
                 "test-data/songs/4.mp3".to_string(),
             ],
-        }
+            false,
+        )
         .filter(default_songs().as_slice());
 
         assert_eq!(1, filtered.len())
@@ -59,19 +193,203 @@ mod tests {
 
     #[test]
     fn basic_playlist_removes_expected_tag_details_3() {
-        let filtered = &Playlist {
-            name: "test".to_string(),
-            songs: vec![
+        let filtered = &Playlist::new(
+            "test".to_string(),
+            vec![
                 "test-data/songs/3.mp3".to_string(),
                 "test-data/songs/4.mp3".to_string(),
                 "test-data/songs/2.mp3".to_string(),
             ],
-        }
+            false,
+        )
+        .filter(default_songs().as_slice());
+
+        assert_eq!(2, filtered.len())
+    }
+
+    #[test]
+    fn basic_playlist_removes_expected_tag_details_4() {
+        let filtered = &Playlist::new(
+            "test".to_string(),
+            vec![
+                "./test-data/songs/1.mp3".to_string(),
+                "test-data/other/../songs/2.mp3".to_string(),
+            ],
+            false,
+        )
         .filter(default_songs().as_slice());
 
         assert_eq!(2, filtered.len())
     }
 
+    #[test]
+    fn basic_playlist_removes_expected_tag_details_5() {
+        let playlist = Playlist::new(
+            "test".to_string(),
+            vec!["TEST-DATA/Songs/1.MP3".to_string()],
+            false,
+        );
+        assert_eq!(0, playlist.filter(default_songs().as_slice()).len());
+
+        let playlist = Playlist::new(
+            "test".to_string(),
+            vec!["TEST-DATA/Songs/1.MP3".to_string()],
+            true,
+        );
+        assert_eq!(1, playlist.filter(default_songs().as_slice()).len());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn basic_playlist_removes_expected_tag_details_6() {
+        let dir = std::env::temp_dir().join("playlist-maker-playlist-symlink-6");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let song = dir.join("song.mp3");
+        std::fs::write(&song, b"").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("link")).unwrap();
+
+        let filtered = Playlist::new(
+            "test".to_string(),
+            vec![dir.join("link/song.mp3").to_string_lossy().to_string()],
+            false,
+        )
+        .filter(&[TagDetails {
+            path: song.to_string_lossy().to_string(),
+            ..Default::default()
+        }]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(1, filtered.len())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn basic_playlist_removes_expected_tag_details_7() {
+        let dir = std::env::temp_dir().join("playlist-maker-playlist-symlink-7");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("music")).unwrap();
+        let song = dir.join("music/song.mp3");
+        std::fs::write(&song, b"").unwrap();
+        std::os::unix::fs::symlink(dir.join("music"), dir.join("link")).unwrap();
+
+        let filtered = Playlist::new(
+            "test".to_string(),
+            vec![song.to_string_lossy().to_string()],
+            false,
+        )
+        .filter(&[TagDetails {
+            path: dir.join("link/song.mp3").to_string_lossy().to_string(),
+            ..Default::default()
+        }]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(1, filtered.len())
+    }
+
+    #[test]
+    fn ensure_fn_position_works_as_expected_1() {
+        let playlist = Playlist::new(
+            "test".to_string(),
+            vec![
+                "test-data/songs/3.mp3".to_string(),
+                "test-data/songs/1.mp3".to_string(),
+                "test-data/songs/3.mp3".to_string(),
+            ],
+            false,
+        );
+
+        assert_eq!(playlist.position("test-data/songs/3.mp3"), Some(1));
+        assert_eq!(playlist.position("test-data/songs/1.mp3"), Some(2));
+        assert_eq!(playlist.position("test-data/songs/2.mp3"), None);
+    }
+
+    #[test]
+    fn ensure_fn_filter_by_position_works_as_expected_1() {
+        let playlist = Playlist::new(
+            "test".to_string(),
+            vec![
+                "test-data/songs/3.mp3".to_string(),
+                "test-data/songs/1.mp3".to_string(),
+            ],
+            false,
+        );
+
+        let filtered = playlist.filter_by_position(default_songs().as_slice(), |e| e > 1);
+
+        assert_eq!(1, filtered.len());
+        assert_eq!(filtered[0].path, "test-data/songs/1.mp3");
+    }
+
+    #[test]
+    fn ensure_fn_resolve_works_as_expected_1() {
+        let dir = Path::new("test-data");
+
+        assert_eq!(
+            resolve("songs/1.mp3", dir).as_deref(),
+            Some("test-data/songs/1.mp3")
+        );
+        assert_eq!(
+            resolve("/music/1.mp3", dir).as_deref(),
+            Some("/music/1.mp3")
+        );
+    }
+
+    #[test]
+    fn ensure_fn_resolve_works_as_expected_2() {
+        let dir = Path::new("test-data");
+
+        assert_eq!(
+            resolve("file:///music/Beyonc%C3%A9/1.mp3", dir).as_deref(),
+            Some("/music/Beyoncé/1.mp3")
+        );
+        assert_eq!(
+            resolve("file://localhost/music/1.mp3", dir).as_deref(),
+            Some("/music/1.mp3")
+        );
+        assert_eq!(resolve("http://radio.example.com/stream", dir), None);
+    }
+
+    #[test]
+    fn ensure_fn_resolve_works_as_expected_3() {
+        let dir = Path::new("test-data");
+
+        assert_eq!(
+            resolve("test-data/songs/1.mp3", dir).as_deref(),
+            Some("test-data/songs/1.mp3")
+        );
+        assert_eq!(
+            resolve("missing/1.mp3", dir).as_deref(),
+            Some("test-data/missing/1.mp3")
+        );
+    }
+
+    #[test]
+    fn ensure_fn_relocate_works_as_expected_1() {
+        let dir = Path::new("/music/playlists");
+        let moved = |path: &str| match path {
+            "/music/playlists/../Joji/1.mp3" | "/music/Joji/1.mp3" => {
+                Some(PathBuf::from("/music/Joji/Nectar/01 Ew.mp3"))
+            }
+            _ => None,
+        };
+
+        assert_eq!(
+            relocate("../Joji/1.mp3", dir, &moved).as_deref(),
+            Some("../Joji/Nectar/01 Ew.mp3")
+        );
+        assert_eq!(
+            relocate("/music/Joji/1.mp3", dir, &moved).as_deref(),
+            Some("/music/Joji/Nectar/01 Ew.mp3")
+        );
+        assert_eq!(
+            relocate("file:///music/Joji/1.mp3", dir, &moved).as_deref(),
+            Some("file:///music/Joji/Nectar/01%20Ew.mp3")
+        );
+        assert_eq!(relocate("/music/Joji/2.mp3", dir, &moved), None);
+        assert_eq!(relocate("@query:Rap", dir, &moved), None);
+    }
+
     fn default_songs() -> Vec<TagDetails> {
         let info1 = TagDetails {
             path: "test-data/songs/1.mp3".to_string(),
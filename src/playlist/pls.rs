@@ -0,0 +1,147 @@
+use crate::playlist;
+use crate::playlist::{lines_with_breaks, m3u, resolve};
+use crate::tag::details::TagDetails;
+use std::path::{Path, PathBuf};
+
+/// PLS playlists don't declare their encoding, they are read like plain M3U ones: as UTF-8 when
+/// valid and as Latin-1 otherwise
+pub fn decode(bytes: Vec<u8>) -> String {
+    m3u::decode(bytes, false)
+}
+
+/// Paths of the songs in a PLS playlist, ordered by their entry number and resolved as in `resolve`
+pub fn parse(content: &str, dir: &Path) -> Vec<String> {
+    let mut entries = content
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once('=')?;
+            let number = key
+                .get(..4)
+                .filter(|prefix| prefix.eq_ignore_ascii_case("file"))
+                .and_then(|_| key[4..].parse::<u32>().ok())?;
+            Some((number, resolve(value.trim(), dir)?))
+        })
+        .collect::<Vec<(u32, String)>>();
+    entries.sort_by_key(|(number, _)| *number);
+    entries.into_iter().map(|(_, path)| path).collect()
+}
+
+/// PLS playlist with the entries of moved songs replaced as in `playlist::relocate`, everything
+/// else written as it was
+pub fn relocate(content: &str, dir: &Path, moved: &impl Fn(&str) -> Option<PathBuf>) -> String {
+    lines_with_breaks(content)
+        .map(|(line, end)| {
+            line.split_once('=')
+                .filter(|(key, _)| {
+                    let key = key.trim().trim_start_matches('\u{feff}');
+                    key.get(..4)
+                        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("file"))
+                        && key[4..].parse::<u32>().is_ok()
+                })
+                .and_then(|(key, value)| {
+                    let path = playlist::relocate(value.trim(), dir, moved)?;
+                    Some(format!("{}={}{}", key, path, end))
+                })
+                .unwrap_or_else(|| format!("{}{}", line, end))
+        })
+        .collect()
+}
+
+/// PLS playlist, `paths` being the paths of `songs` as they should be written
+pub fn format(songs: &[TagDetails], paths: Vec<String>) -> String {
+    let mut lines = vec![String::from("[playlist]")];
+    for (number, (song, path)) in songs.iter().zip(paths).enumerate() {
+        lines.push(format!("File{}={}", number + 1, path));
+        lines.push(format!("Title{}={}", number + 1, song.display_name()));
+        lines.push(format!(
+            "Length{}={}",
+            number + 1,
+            song.duration.map_or(-1, |e| e as i64)
+        ));
+    }
+    lines.push(format!("NumberOfEntries={}", songs.len()));
+    lines.push(String::from("Version=2"));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::playlist::pls::{decode, format, parse, relocate};
+    use crate::tag::details::TagDetails;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn ensure_fn_parse_works_as_expected_1() {
+        let content = "[playlist]\r\nFile2=/music/2.mp3\r\nTitle2=Two\r\nFile1=songs/1.mp3\r\nnumberofentries=2\r\nVersion=2\r\n";
+
+        let songs = parse(content, Path::new("test-data"));
+
+        assert_eq!(songs, vec!["test-data/songs/1.mp3", "/music/2.mp3"]);
+    }
+
+    #[test]
+    fn ensure_fn_parse_works_as_expected_2() {
+        let content = "[playlist]\nfile1=http://radio.example.com/stream\nFileX=1.mp3\n";
+
+        let songs = parse(content, Path::new("test-data"));
+
+        assert!(songs.is_empty());
+    }
+
+    #[test]
+    fn ensure_fn_parse_works_as_expected_3() {
+        let mut bytes = b"[playlist]\nFile1=Beyonc".to_vec();
+        bytes.extend([0xe9]);
+        bytes.extend(b".mp3\nNumberOfEntries=1\n");
+
+        let songs = parse(&decode(bytes), Path::new("/music"));
+
+        assert_eq!(songs, vec!["/music/Beyoncé.mp3"]);
+    }
+
+    #[test]
+    fn ensure_fn_format_works_as_expected_1() {
+        let songs = vec![
+            TagDetails {
+                path: "test-data/songs/1.mp3".to_string(),
+                artist: Some("Drake".to_string()),
+                title: Some("Passionfruit".to_string()),
+                duration: Some(298),
+                ..Default::default()
+            },
+            TagDetails {
+                path: "test-data/songs/2.mp3".to_string(),
+                ..Default::default()
+            },
+        ];
+        let paths = songs.iter().map(|song| song.path.clone()).collect();
+
+        assert_eq!(
+            format(&songs, paths),
+            "[playlist]
+File1=test-data/songs/1.mp3
+Title1=Drake - Passionfruit
+Length1=298
+File2=test-data/songs/2.mp3
+Title2=2
+Length2=-1
+NumberOfEntries=2
+Version=2"
+        );
+    }
+
+    #[test]
+    fn ensure_fn_relocate_works_as_expected_1() {
+        let content =
+            "[playlist]\nFile1=/music/Ew.mp3\nTitle1=Ew\nfile2=Run.mp3\nNumberOfEntries=2";
+        let moved = |path: &str| {
+            (path == "/music/Ew.mp3").then(|| PathBuf::from("/music/Joji/Nectar/01 Ew.mp3"))
+        };
+
+        assert_eq!(
+            relocate(content, Path::new("/music"), &moved),
+            "[playlist]\nFile1=/music/Joji/Nectar/01 Ew.mp3\nTitle1=Ew\nfile2=Run.mp3\nNumberOfEntries=2"
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use crate::playlist;
+use crate::playlist::resolve;
+use crate::tag::details::TagDetails;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use quick_xml::escape::{escape, unescape};
+use quick_xml::se::Serializer;
+use quick_xml::SeError;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+const NAMESPACE: &str = "http://xspf.org/ns/0/";
+
+/// Characters escaped in the locations, everything but the unreserved ones and the separator
+const LOCATION: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'/')
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "playlist")]
+struct Xspf {
+    #[serde(rename = "@version")]
+    version: String,
+    #[serde(rename = "@xmlns", default)]
+    xmlns: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(rename = "trackList")]
+    track_list: TrackList,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TrackList {
+    #[serde(rename = "track", default)]
+    tracks: Vec<Track>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Track {
+    location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    creator: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    album: Option<String>,
+    /// Duration in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration: Option<u64>,
+}
+
+/// Paths of the songs in a XSPF playlist, its locations are URIs resolved as in `resolve`
+pub fn parse(content: &str, dir: &Path) -> Option<Vec<String>> {
+    let xspf = quick_xml::de::from_str::<Xspf>(content.trim_start_matches('\u{feff}')).ok()?;
+    Some(
+        xspf.track_list
+            .tracks
+            .into_iter()
+            .filter_map(|track| track.location)
+            .filter_map(|location| match location.contains("://") {
+                true => resolve(location.trim(), dir),
+                false => resolve(
+                    &percent_decode_str(location.trim()).decode_utf8_lossy(),
+                    dir,
+                ),
+            })
+            .collect(),
+    )
+}
+
+/// XSPF playlist with the locations of moved songs replaced as in `playlist::relocate`, everything
+/// else written as it was
+pub fn relocate(content: &str, dir: &Path, moved: &impl Fn(&str) -> Option<PathBuf>) -> String {
+    let mut relocated = String::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("<location>") {
+        let (before, after) = rest.split_at(start + "<location>".len());
+        relocated.push_str(before);
+        let end = after.find("</location>").unwrap_or(after.len());
+        let text = &after[..end];
+        let location = unescape(text.trim()).unwrap_or(Cow::Borrowed(text.trim()));
+        let entry = match location.contains("://") {
+            true => location.to_string(),
+            false => percent_decode_str(&location)
+                .decode_utf8_lossy()
+                .to_string(),
+        };
+        match playlist::relocate(&entry, dir, moved) {
+            Some(path) if path.contains("://") => relocated.push_str(&escape(path)),
+            Some(path) => relocated.push_str(&escape(self::location(&path))),
+            None => relocated.push_str(text),
+        }
+        rest = &after[end..];
+    }
+    relocated.push_str(rest);
+    relocated
+}
+
+/// XSPF playlist, `paths` being the paths of `songs` as they should be written
+pub fn format(
+    title: Option<&str>,
+    songs: &[TagDetails],
+    paths: Vec<String>,
+) -> Result<String, SeError> {
+    let xspf = Xspf {
+        version: String::from("1"),
+        xmlns: String::from(NAMESPACE),
+        title: title.map(|e| e.to_string()),
+        track_list: TrackList {
+            tracks: songs
+                .iter()
+                .zip(paths)
+                .map(|(song, path)| Track {
+                    location: Some(location(&path)),
+                    title: song.title.to_owned(),
+                    creator: song.artist.to_owned(),
+                    album: song.album.to_owned(),
+                    duration: song.duration.map(|e| e * 1000),
+                })
+                .collect(),
+        },
+    };
+
+    let mut content = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    content.push('\n');
+    let mut serializer = Serializer::new(&mut content);
+    serializer.indent(' ', 2);
+    xspf.serialize(serializer)?;
+    Ok(content)
+}
+
+/// URI of a path, relative paths are kept relative (to the playlist)
+pub fn location(path: &str) -> String {
+    let encoded = utf8_percent_encode(path, LOCATION).to_string();
+    if Path::new(path).is_absolute() {
+        format!("file://{}", encoded)
+    } else {
+        encoded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::playlist::xspf::{format, parse, relocate};
+    use crate::tag::details::TagDetails;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn ensure_fn_parse_works_as_expected_1() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <title>Mix</title>
+  <trackList>
+    <track><location>file:///music/Beyonc%C3%A9/1.mp3</location><title>One</title></track>
+    <track><location>songs/2%20two.mp3</location></track>
+    <track><location>http://radio.example.com/stream</location></track>
+    <track><title>No location</title></track>
+  </trackList>
+</playlist>"#;
+
+        let songs = parse(content, Path::new("test-data")).unwrap();
+
+        assert_eq!(
+            songs,
+            vec!["/music/Beyoncé/1.mp3", "test-data/songs/2 two.mp3"]
+        );
+    }
+
+    #[test]
+    fn ensure_fn_parse_works_as_expected_2() {
+        let content = r#"<playlist version="1"><trackList/></playlist>"#;
+
+        assert_eq!(parse(content, Path::new("")), Some(vec![]));
+        assert_eq!(parse("[playlist]", Path::new("")), None);
+    }
+
+    #[test]
+    fn ensure_fn_format_works_as_expected_1() {
+        let songs = vec![
+            TagDetails {
+                path: "/music/Drake/1 Passionfruit.mp3".to_string(),
+                artist: Some("Drake".to_string()),
+                title: Some("Passionfruit".to_string()),
+                album: Some("More Life".to_string()),
+                duration: Some(298),
+                ..Default::default()
+            },
+            TagDetails {
+                path: "songs/R&B.mp3".to_string(),
+                ..Default::default()
+            },
+        ];
+        let paths = songs.iter().map(|song| song.path.clone()).collect();
+
+        assert_eq!(
+            format(Some("Mix"), &songs, paths).unwrap(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <title>Mix</title>
+  <trackList>
+    <track>
+      <location>file:///music/Drake/1%20Passionfruit.mp3</location>
+      <title>Passionfruit</title>
+      <creator>Drake</creator>
+      <album>More Life</album>
+      <duration>298000</duration>
+    </track>
+    <track>
+      <location>songs/R%26B.mp3</location>
+    </track>
+  </trackList>
+</playlist>"#
+        );
+    }
+
+    #[test]
+    fn ensure_fn_format_works_as_expected_2() {
+        let songs = vec![TagDetails {
+            path: "/music/1.mp3".to_string(),
+            ..Default::default()
+        }];
+        let paths = songs.iter().map(|song| song.path.clone()).collect();
+
+        let content = format(None, &songs, paths).unwrap();
+
+        assert_eq!(
+            parse(&content, Path::new("")),
+            Some(vec!["/music/1.mp3".to_string()])
+        );
+    }
+
+    #[test]
+    fn ensure_fn_relocate_works_as_expected_1() {
+        let content = r#"<track><location>file:///music/Ew.mp3</location></track>
+<track><location>Ew.mp3</location></track>
+<track><location>Run.mp3</location></track>"#;
+        let moved = |path: &str| {
+            (path == "/music/Ew.mp3").then(|| PathBuf::from("/music/Joji/Nectar/01 Ew.mp3"))
+        };
+
+        assert_eq!(
+            relocate(content, Path::new("/music"), &moved),
+            r#"<track><location>file:///music/Joji/Nectar/01%20Ew.mp3</location></track>
+<track><location>Joji/Nectar/01%20Ew.mp3</location></track>
+<track><location>Run.mp3</location></track>"#
+        );
+    }
+}
@@ -0,0 +1,355 @@
+use crate::tag::details::TagDetails;
+use crate::utils;
+use serde::Serializer;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Highest track number (or total of tracks) taken as real, higher ones are mistakes in the tags
+/// and listing the tracks missing up to them would take forever
+pub const MAX_TRACK: u32 = 999;
+
+/// Songs sharing the same album and album artist
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Album {
+    pub album: String,
+    pub album_artist: Option<String>,
+    pub tracks: usize,
+    /// Whether every track of the numbering is there, albums with songs without a track number
+    /// (or with one above `MAX_TRACK`) are never complete
+    pub complete: bool,
+    /// Track numbers missing from the numbering, as `<disc>-<track>` for albums with many discs
+    pub missing: Vec<String>,
+    /// Sum of the known durations, in seconds
+    pub duration: Option<u64>,
+    /// Songs in disc and track order, written as their paths
+    #[serde(serialize_with = "paths")]
+    pub songs: Vec<TagDetails>,
+}
+
+impl Album {
+    fn new(album: String, album_artist: Option<String>, mut songs: Vec<TagDetails>) -> Self {
+        songs.sort_by_cached_key(|song| {
+            (
+                number(song.disc.as_deref()).map(|(disc, _)| disc),
+                number(song.track.as_deref()).map(|(track, _)| track),
+                song.path.to_owned(),
+            )
+        });
+
+        let mut numbered = true;
+        let mut discs = BTreeMap::<u32, (BTreeSet<u32>, u32)>::new();
+        for song in songs.iter() {
+            match track_number(song) {
+                Some((track, total)) => {
+                    let disc = number(song.disc.as_deref()).map_or(1, |(disc, _)| disc);
+                    let (tracks, last) = discs.entry(disc).or_default();
+                    tracks.insert(track);
+                    *last = (*last).max(track).max(total.unwrap_or_default());
+                }
+                None => numbered = false,
+            }
+        }
+        let many = discs.len() > 1;
+        let missing = discs
+            .iter()
+            .flat_map(|(disc, (tracks, last))| {
+                (1..=*last)
+                    .filter(|track| !tracks.contains(track))
+                    .map(move |track| match many {
+                        false => track.to_string(),
+                        true => format!("{}-{}", disc, track),
+                    })
+            })
+            .collect::<Vec<String>>();
+
+        Album {
+            album,
+            album_artist,
+            tracks: songs.len(),
+            complete: numbered && missing.is_empty(),
+            missing,
+            duration: songs
+                .iter()
+                .filter_map(|song| song.duration)
+                .reduce(|total, duration| total + duration),
+            songs,
+        }
+    }
+}
+
+/// Albums of the songs (songs without an album are left out), by album artist and name
+pub fn group(songs: &[TagDetails], complete: bool) -> Vec<Album> {
+    let mut seen = HashSet::<&str>::new();
+    let mut groups = HashMap::<(String, Option<String>), Vec<TagDetails>>::new();
+    for song in songs {
+        if let Some(album) = song.album.as_deref().filter(|album| !album.is_empty()) {
+            if seen.insert(song.path.as_str()) {
+                groups
+                    .entry((album.to_string(), song.album_artist.to_owned()))
+                    .or_default()
+                    .push(song.to_owned());
+            }
+        }
+    }
+
+    let mut albums = groups
+        .into_iter()
+        .map(|((album, album_artist), songs)| Album::new(album, album_artist, songs))
+        .filter(|album| album.complete || !complete)
+        .collect::<Vec<Album>>();
+    albums.sort_by_cached_key(|album| {
+        (
+            album.album_artist.is_none(),
+            album.album_artist.to_owned(),
+            album.album.to_owned(),
+        )
+    });
+    albums
+}
+
+/// Songs of the albums, album after album
+pub fn songs(albums: Vec<Album>) -> Vec<TagDetails> {
+    albums.into_iter().flat_map(|album| album.songs).collect()
+}
+
+/// Aligned table with a header and one album per line
+pub fn table(albums: &[Album]) -> String {
+    let header = ["Album Artist", "Album", "Tracks", "Complete", "Missing"].map(String::from);
+    let rows = albums
+        .iter()
+        .map(|album| {
+            [
+                album
+                    .album_artist
+                    .to_owned()
+                    .unwrap_or_else(|| String::from("(none)")),
+                album.album.to_owned(),
+                album.tracks.to_string(),
+                String::from(if album.complete { "yes" } else { "no" }),
+                album.missing.join(", "),
+            ]
+        })
+        .collect::<Vec<[String; 5]>>();
+
+    let widths = (0..5)
+        .map(|column| {
+            rows.iter()
+                .chain([&header])
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<usize>>();
+    [&header]
+        .into_iter()
+        .chain(rows.iter())
+        .map(|row| {
+            format!(
+                "{:<artist$}  {:<album$}  {:>tracks$}  {:<complete$}  {}",
+                row[0],
+                row[1],
+                row[2],
+                row[3],
+                row[4],
+                artist = widths[0],
+                album = widths[1],
+                tracks = widths[2],
+                complete = widths[3]
+            )
+            .trim_end()
+            .to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Csv with one album per row, missing tracks separated by spaces and without the songs
+pub fn to_csv(albums: &[Album]) -> String {
+    utils::csv::to_string(
+        &[
+            "album",
+            "album_artist",
+            "tracks",
+            "complete",
+            "missing",
+            "duration",
+        ],
+        albums.iter().map(|album| {
+            (
+                &album.album,
+                album.album_artist.as_deref().unwrap_or_default(),
+                album.tracks,
+                album.complete,
+                album.missing.join(" "),
+                album.duration.map(|e| e.to_string()).unwrap_or_default(),
+            )
+        }),
+    )
+}
+
+/// Number and total of a track or disc tag, e.g. `3/12`
+pub fn number(value: Option<&str>) -> Option<(u32, Option<u32>)> {
+    let value = value?;
+    let (number, total) = match value.split_once('/') {
+        Some((number, total)) => (number, total.trim().parse().ok()),
+        None => (value, None),
+    };
+    Some((number.trim().parse().ok()?, total))
+}
+
+/// Track number and total of a song, left out when they are above `MAX_TRACK`
+pub fn track_number(song: &TagDetails) -> Option<(u32, Option<u32>)> {
+    let (track, total) = number(song.track.as_deref())?;
+    (track <= MAX_TRACK).then_some((track, total.filter(|total| *total <= MAX_TRACK)))
+}
+
+fn paths<S: Serializer>(songs: &[TagDetails], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(songs.iter().map(|song| song.path.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::albums::{group, number, songs, table, to_csv};
+    use crate::tag::details::TagDetails;
+
+    #[test]
+    fn ensure_fn_number_works_as_expected_1() {
+        assert_eq!(number(Some("3")), Some((3, None)));
+        assert_eq!(number(Some(" 3/12")), Some((3, Some(12))));
+        assert_eq!(number(Some("A1")), None);
+        assert_eq!(number(None), None);
+    }
+
+    #[test]
+    fn ensure_fn_group_works_as_expected_1() {
+        let albums = group(&default_songs(), false);
+
+        assert_eq!(albums.len(), 3);
+        let nectar = albums.first().unwrap();
+        assert_eq!(nectar.album, "Nectar");
+        assert_eq!(nectar.album_artist.as_deref(), Some("Joji"));
+        assert_eq!(nectar.tracks, 2);
+        assert!(!nectar.complete);
+        assert_eq!(nectar.missing, vec!["3", "4"]);
+        let scorpion = albums.get(1).unwrap();
+        assert_eq!(scorpion.album, "Scorpion");
+        assert!(scorpion.complete);
+        assert_eq!(scorpion.duration, Some(300));
+        assert_eq!(
+            scorpion
+                .songs
+                .iter()
+                .map(|song| song.path.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["a/1.mp3", "b/2.mp3", "b/1.mp3"]
+        );
+        let unknown = albums.get(2).unwrap();
+        assert_eq!(unknown.album_artist, None);
+        assert!(!unknown.complete);
+        assert!(unknown.missing.is_empty());
+    }
+
+    #[test]
+    fn ensure_fn_group_works_as_expected_2() {
+        let albums = group(&default_songs(), true);
+
+        assert_eq!(albums.len(), 1);
+        assert_eq!(
+            songs(albums)
+                .iter()
+                .map(|song| song.path.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["a/1.mp3", "b/2.mp3", "b/1.mp3"]
+        );
+    }
+
+    #[test]
+    fn ensure_fn_group_works_as_expected_3() {
+        let songs = vec![
+            song("1.mp3", "Scorpion", Some("1/2"), Some("1")),
+            song("2.mp3", "Scorpion", Some("1/2"), Some("3")),
+            song("3.mp3", "Scorpion", Some("2/2"), Some("2/2")),
+        ];
+
+        let albums = group(&songs, false);
+
+        assert_eq!(albums.first().unwrap().missing, vec!["1-2", "2-1"]);
+    }
+
+    #[test]
+    fn ensure_fn_group_works_as_expected_4() {
+        let songs = vec![
+            song("n/1.mp3", "Nectar", None, Some("1/4294967295")),
+            song("s/1.mp3", "Scorpion", None, Some("1")),
+            song("s/2.mp3", "Scorpion", None, Some("4000000000")),
+        ];
+
+        let albums = group(&songs, false);
+
+        let nectar = albums.first().unwrap();
+        assert!(nectar.complete);
+        assert!(nectar.missing.is_empty());
+        let scorpion = albums.get(1).unwrap();
+        assert_eq!(scorpion.tracks, 2);
+        assert!(!scorpion.complete);
+        assert!(scorpion.missing.is_empty());
+    }
+
+    #[test]
+    fn ensure_fn_table_works_as_expected_1() {
+        let output = table(&group(&default_songs(), false));
+
+        assert_eq!(
+            output,
+            "Album Artist  Album     Tracks  Complete  Missing\n\
+             Joji          Nectar         2  no        3, 4\n\
+             Joji          Scorpion       3  yes\n\
+             (none)        Singles        1  no"
+        );
+    }
+
+    #[test]
+    fn ensure_fn_to_csv_works_as_expected_1() {
+        let output = to_csv(&group(&default_songs(), false));
+
+        assert_eq!(
+            output,
+            r#""album","album_artist","tracks","complete","missing","duration"
+"Nectar","Joji","2","false","3 4",""
+"Scorpion","Joji","3","true","","300"
+"Singles","","1","false","","""#
+        );
+    }
+
+    fn song(path: &str, album: &str, disc: Option<&str>, track: Option<&str>) -> TagDetails {
+        TagDetails {
+            path: path.to_string(),
+            album: Some(album.to_string()),
+            album_artist: Some(String::from("Joji")),
+            disc: disc.map(String::from),
+            track: track.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    fn default_songs() -> Vec<TagDetails> {
+        let mut single = song("c/1.mp3", "Singles", None, None);
+        single.album_artist = None;
+        let mut first = song("a/1.mp3", "Scorpion", Some("1"), Some("1/3"));
+        first.duration = Some(100);
+        let mut last = song("b/1.mp3", "Scorpion", Some("1"), Some("3"));
+        last.duration = Some(200);
+        vec![
+            song("n/2.mp3", "Nectar", None, Some("2")),
+            last.to_owned(),
+            song("b/2.mp3", "Scorpion", Some("1"), Some("2")),
+            song("n/1.mp3", "Nectar", None, Some("1/4")),
+            first,
+            last,
+            single,
+            TagDetails {
+                path: "d/1.mp3".to_string(),
+                ..Default::default()
+            },
+        ]
+    }
+}
@@ -0,0 +1,112 @@
+use std::fs;
+use std::path::Path;
+use std::process::exit;
+
+/// A query of a batch file, written to a playlist named after it
+#[derive(Debug, PartialEq)]
+pub struct NamedQuery {
+    pub name: String,
+    pub query: String,
+}
+
+/// Reads the queries of a batch file, one `<name> = <query>` per line. Empty lines and lines
+/// starting with `#` are skipped
+pub fn read(file: &Path) -> Vec<NamedQuery> {
+    let content = fs::read_to_string(file).unwrap_or_else(|_| {
+        println!(
+            "batch file `{}` does not exist or is invalid!",
+            file.display()
+        );
+        exit(2);
+    });
+    parse(&content).unwrap_or_else(|line| {
+        println!(
+            "batch file `{}` has an invalid line {} (expected `<name> = <query>`)!",
+            file.display(),
+            line
+        );
+        exit(2);
+    })
+}
+
+/// Queries of a batch (or query library) file or the number of the first invalid line
+pub fn parse(content: &str) -> Result<Vec<NamedQuery>, usize> {
+    let mut queries = Vec::<NamedQuery>::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((name, query))
+                if is_valid_name(name.trim())
+                    && !query.trim().is_empty()
+                    && queries.iter().all(|other| other.name != name.trim()) =>
+            {
+                queries.push(NamedQuery {
+                    name: name.trim().to_string(),
+                    query: query.trim().to_string(),
+                })
+            }
+            _ => return Err(number + 1),
+        }
+    }
+    Ok(queries)
+}
+
+/// Names are used as file names (relative to the output directory) so they can't leave it
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('/')
+        && name
+            .split('/')
+            .all(|part| !part.is_empty() && part != "." && part != "..")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::batch::{parse, NamedQuery};
+
+    #[test]
+    fn ensure_fn_parse_works_as_expected_1() {
+        let content = r#"
+# weekly playlists
+summer = Play(Genre("Pop") & Year("2023"))
+gym=Play(C_Genre("Rock"))
+
+2023/chill = Play(Genre("Jazz"))
+"#;
+
+        let queries = parse(content).unwrap();
+
+        assert_eq!(
+            queries,
+            vec![
+                NamedQuery {
+                    name: "summer".to_string(),
+                    query: r#"Play(Genre("Pop") & Year("2023"))"#.to_string(),
+                },
+                NamedQuery {
+                    name: "gym".to_string(),
+                    query: r#"Play(C_Genre("Rock"))"#.to_string(),
+                },
+                NamedQuery {
+                    name: "2023/chill".to_string(),
+                    query: r#"Play(Genre("Jazz"))"#.to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn ensure_fn_parse_works_as_expected_2() {
+        assert_eq!(parse("summer Play(Genre(\"Pop\"))"), Err(1));
+        assert_eq!(parse("\n = Play(Genre(\"Pop\"))"), Err(2));
+        assert_eq!(parse("../summer = Play(Genre(\"Pop\"))"), Err(1));
+        assert_eq!(parse("summer = "), Err(1));
+        assert_eq!(
+            parse("a = Play(Year(\"1\"))\na = Play(Year(\"2\"))"),
+            Err(2)
+        );
+    }
+}
@@ -0,0 +1,454 @@
+use crate::query::albums::{number, MAX_TRACK};
+use crate::tag::audio::id3_version;
+use crate::tag::details::TagDetails;
+use crate::utils;
+use rayon::prelude::*;
+use serde::Serializer;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::Path;
+
+/// Rules run by a `Check` query, in the order their problems are reported for a song
+#[derive(Debug, Clone, Copy, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Lint {
+    /// Songs of an album without a track number
+    MissingTrack,
+    /// Songs of an album with a track number or total above `MAX_TRACK`, left out of the numbering
+    TrackRange,
+    /// Songs of an album sharing the same disc and track number
+    DuplicateTrack,
+    /// Track numbers missing from the numbering of an album
+    TrackGap,
+    /// Songs of the same album folder with different album artists
+    AlbumArtist,
+    /// Songs of an album with different years
+    Year,
+    /// Tags starting or ending with whitespace
+    Whitespace,
+    /// Songs of an album with different versions of id3 tags
+    Id3Version,
+}
+
+/// A problem found by a rule, with the songs having it
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Problem {
+    pub lint: Lint,
+    pub message: String,
+    /// Songs in path order, written as their paths
+    #[serde(serialize_with = "paths")]
+    pub songs: Vec<TagDetails>,
+}
+
+impl Problem {
+    fn new(lint: Lint, message: String, songs: &[&TagDetails]) -> Self {
+        let mut songs = songs
+            .iter()
+            .map(|song| (*song).to_owned())
+            .collect::<Vec<TagDetails>>();
+        songs.sort_by(|song, other| song.path.cmp(&other.path));
+        Problem {
+            lint,
+            message,
+            songs,
+        }
+    }
+}
+
+/// Problems of the songs by path of their first song. Albums are the songs of a folder sharing
+/// the same album tag, songs without an album are only checked for whitespace
+pub fn check(songs: &[TagDetails]) -> Vec<Problem> {
+    let mut seen = HashSet::<&str>::new();
+    let songs = songs
+        .iter()
+        .filter(|song| seen.insert(song.path.as_str()))
+        .collect::<Vec<&TagDetails>>();
+
+    let mut folders = BTreeMap::<&str, Vec<&TagDetails>>::new();
+    let mut albums = BTreeMap::<(&str, &str), Vec<&TagDetails>>::new();
+    for song in songs.iter() {
+        if let Some(album) = song.album.as_deref().filter(|album| !album.is_empty()) {
+            let folder = folder(song);
+            folders.entry(folder).or_default().push(song);
+            albums.entry((folder, album)).or_default().push(song);
+        }
+    }
+
+    let mut problems = songs
+        .iter()
+        .filter_map(|song| whitespace(song))
+        .collect::<Vec<Problem>>();
+    problems.extend(
+        folders
+            .iter()
+            .filter_map(|(folder, songs)| album_artist(folder, songs)),
+    );
+    let versions = albums
+        .values()
+        .flatten()
+        .collect::<Vec<_>>()
+        .par_iter()
+        .map(|song| (song.path.as_str(), id3_version(Path::new(&song.path))))
+        .collect::<BTreeMap<&str, Option<String>>>();
+    for ((_, album), songs) in albums.iter() {
+        problems.extend(tracks(album, songs));
+        problems.extend(differences(Lint::Year, "years", album, songs, |song| {
+            song.year_number().map(|year| year.to_string())
+        }));
+        problems.extend(differences(
+            Lint::Id3Version,
+            "id3 versions",
+            album,
+            songs,
+            |song| versions[song.path.as_str()].to_owned(),
+        ));
+    }
+
+    problems.sort_by_cached_key(|problem| (problem.songs[0].path.to_owned(), problem.lint));
+    problems
+}
+
+/// Songs having a problem, in the order of their first problem
+pub fn songs(problems: Vec<Problem>) -> Vec<TagDetails> {
+    let mut seen = HashSet::<String>::new();
+    problems
+        .into_iter()
+        .flat_map(|problem| problem.songs)
+        .filter(|song| seen.insert(song.path.to_owned()))
+        .collect()
+}
+
+/// Each problem with its songs below it, followed by the number of problems and files
+pub fn report(problems: &[Problem]) -> String {
+    let mut lines = problems
+        .iter()
+        .map(|problem| {
+            let mut lines = vec![format!("{:?}: {}", problem.lint, problem.message)];
+            lines.extend(problem.songs.iter().map(|song| format!("  {}", song.path)));
+            lines.join("\n")
+        })
+        .collect::<Vec<String>>();
+    lines.push(format!(
+        "{} problems in {} files",
+        problems.len(),
+        problems
+            .iter()
+            .flat_map(|problem| problem.songs.iter().map(|song| song.path.as_str()))
+            .collect::<HashSet<&str>>()
+            .len()
+    ));
+    lines.join("\n\n")
+}
+
+/// Csv with one song per row and the rule and message of its problem
+pub fn to_csv(problems: &[Problem]) -> String {
+    utils::csv::to_string(
+        &["lint", "message", "path"],
+        problems.iter().flat_map(|problem| {
+            problem
+                .songs
+                .iter()
+                .map(|song| (problem.lint, &problem.message, &song.path))
+        }),
+    )
+}
+
+fn folder(song: &TagDetails) -> &str {
+    Path::new(&song.path)
+        .parent()
+        .and_then(|parent| parent.to_str())
+        .unwrap_or_default()
+}
+
+fn whitespace(song: &TagDetails) -> Option<Problem> {
+    let fields = TagDetails::FIELDS
+        .iter()
+        .filter(|name| !["path", "duration", "modified", "size"].contains(name))
+        .filter(|name| {
+            song.field(name)
+                .is_some_and(|value| value.trim() != value.as_str())
+        })
+        .copied()
+        .collect::<Vec<&str>>();
+    (!fields.is_empty()).then(|| {
+        Problem::new(
+            Lint::Whitespace,
+            format!("{} start or end with whitespace", fields.join(", ")),
+            &[song],
+        )
+    })
+}
+
+fn album_artist(folder: &str, songs: &[&TagDetails]) -> Option<Problem> {
+    let artists = songs
+        .iter()
+        .map(|song| song.album_artist.as_deref().unwrap_or("(none)"))
+        .collect::<BTreeSet<&str>>();
+    (artists.len() > 1).then(|| {
+        Problem::new(
+            Lint::AlbumArtist,
+            format!(
+                "album artists of `{}` differ: {}",
+                folder,
+                artists.into_iter().collect::<Vec<&str>>().join(", ")
+            ),
+            songs,
+        )
+    })
+}
+
+/// Songs without a track number or with one out of range, track numbers used many times and gaps
+/// in the numbering, disc after disc. Tracks are written as `<disc>-<track>` for albums with many
+/// discs
+fn tracks(album: &str, songs: &[&TagDetails]) -> Vec<Problem> {
+    let mut problems = vec![];
+    let mut unnumbered = vec![];
+    let mut out_of_range = vec![];
+    let mut discs = BTreeMap::<u32, (BTreeMap<u32, Vec<&TagDetails>>, u32)>::new();
+    for song in songs {
+        match number(song.track.as_deref()) {
+            Some((track, total))
+                if track > MAX_TRACK || total.is_some_and(|total| total > MAX_TRACK) =>
+            {
+                out_of_range.push(*song)
+            }
+            Some((track, total)) => {
+                let disc = number(song.disc.as_deref()).map_or(1, |(disc, _)| disc);
+                let (tracks, last) = discs.entry(disc).or_default();
+                tracks.entry(track).or_default().push(song);
+                *last = (*last).max(track).max(total.unwrap_or_default());
+            }
+            None => unnumbered.push(*song),
+        }
+    }
+    if !unnumbered.is_empty() {
+        problems.push(Problem::new(
+            Lint::MissingTrack,
+            format!(
+                "{} songs of `{}` have no track number",
+                unnumbered.len(),
+                album
+            ),
+            &unnumbered,
+        ));
+    }
+    if !out_of_range.is_empty() {
+        problems.push(Problem::new(
+            Lint::TrackRange,
+            format!(
+                "{} songs of `{}` have a track number or total above {}",
+                out_of_range.len(),
+                album,
+                MAX_TRACK
+            ),
+            &out_of_range,
+        ));
+    }
+
+    let many = discs.len() > 1;
+    let name = |disc: u32, track: u32| match many {
+        false => track.to_string(),
+        true => format!("{}-{}", disc, track),
+    };
+    for (disc, (tracks, last)) in discs.iter() {
+        for (track, songs) in tracks.iter().filter(|(_, songs)| songs.len() > 1) {
+            problems.push(Problem::new(
+                Lint::DuplicateTrack,
+                format!(
+                    "track {} of `{}` is used {} times",
+                    name(*disc, *track),
+                    album,
+                    songs.len()
+                ),
+                songs,
+            ));
+        }
+        let missing = (1..=*last)
+            .filter(|track| !tracks.contains_key(track))
+            .map(|track| name(*disc, track))
+            .collect::<Vec<String>>();
+        if !missing.is_empty() {
+            problems.push(Problem::new(
+                Lint::TrackGap,
+                format!("tracks {} of `{}` are missing", missing.join(", "), album),
+                &tracks.values().flatten().copied().collect::<Vec<_>>(),
+            ));
+        }
+    }
+    problems
+}
+
+/// Problem when the songs of an album have different known values, songs without one are left out
+fn differences(
+    lint: Lint,
+    label: &str,
+    album: &str,
+    songs: &[&TagDetails],
+    value: impl Fn(&TagDetails) -> Option<String>,
+) -> Option<Problem> {
+    let values = songs
+        .iter()
+        .filter_map(|song| value(song))
+        .collect::<BTreeSet<String>>();
+    (values.len() > 1).then(|| {
+        Problem::new(
+            lint,
+            format!(
+                "{} of `{}` differ: {}",
+                label,
+                album,
+                values.into_iter().collect::<Vec<String>>().join(", ")
+            ),
+            songs,
+        )
+    })
+}
+
+fn paths<S: Serializer>(songs: &[TagDetails], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(songs.iter().map(|song| song.path.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::check::{check, report, songs, to_csv, Lint};
+    use crate::tag::details::TagDetails;
+
+    #[test]
+    fn ensure_fn_check_works_as_expected_1() {
+        let problems = check(&default_songs());
+
+        assert_eq!(
+            problems
+                .iter()
+                .map(|problem| (problem.lint, problem.message.as_str()))
+                .collect::<Vec<(Lint, &str)>>(),
+            vec![
+                (Lint::TrackGap, "tracks 3 of `Nectar` are missing"),
+                (
+                    Lint::AlbumArtist,
+                    "album artists of `a` differ: (none), Joji"
+                ),
+                (Lint::Year, "years of `Nectar` differ: 2020, 2021"),
+                (
+                    Lint::DuplicateTrack,
+                    "track 1-2 of `Scorpion` is used 2 times"
+                ),
+                (Lint::TrackGap, "tracks 1-1 of `Scorpion` are missing"),
+                (
+                    Lint::MissingTrack,
+                    "1 songs of `Scorpion` have no track number"
+                ),
+                (
+                    Lint::Whitespace,
+                    "title, genre start or end with whitespace"
+                ),
+            ]
+        );
+        assert_eq!(
+            problems[5]
+                .songs
+                .iter()
+                .map(|song| song.path.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["b/4.mp3"]
+        );
+    }
+
+    #[test]
+    fn ensure_fn_check_works_as_expected_2() {
+        let songs = ["test-data/songs/id3v2.3.mp3", "test-data/songs/id3v2.4.mp3"]
+            .iter()
+            .map(|path| TagDetails {
+                path: path.to_string(),
+                album: Some(String::from("Tests")),
+                track: Some(path[22..23].to_string()),
+                ..Default::default()
+            })
+            .collect::<Vec<TagDetails>>();
+
+        let problems = check(&songs);
+
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].lint, Lint::TrackGap);
+        assert_eq!(problems[1].lint, Lint::Id3Version);
+        assert_eq!(
+            problems[1].message,
+            "id3 versions of `Tests` differ: 2.3, 2.4"
+        );
+    }
+
+    #[test]
+    fn ensure_fn_check_works_as_expected_3() {
+        let songs = vec![
+            song("a/1.mp3", "Nectar", None, Some("1")),
+            song("a/2.mp3", "Nectar", None, Some("2/4294967295")),
+            song("a/3.mp3", "Nectar", None, Some("4000000000")),
+        ];
+
+        let problems = check(&songs);
+
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].lint, Lint::TrackRange);
+        assert_eq!(
+            problems[0].message,
+            "2 songs of `Nectar` have a track number or total above 999"
+        );
+        assert_eq!(problems[0].songs.len(), 2);
+    }
+
+    #[test]
+    fn ensure_fn_report_works_as_expected_1() {
+        let problems = check(&default_songs()[..3]);
+
+        assert_eq!(
+            report(&problems),
+            "TrackGap: tracks 3 of `Nectar` are missing\n  a/1.mp3\n  a/2.mp3\n  a/4.mp3\n\n\
+             AlbumArtist: album artists of `a` differ: (none), Joji\n  a/1.mp3\n  a/2.mp3\n  a/4.mp3\n\n\
+             Year: years of `Nectar` differ: 2020, 2021\n  a/1.mp3\n  a/2.mp3\n  a/4.mp3\n\n\
+             3 problems in 3 files"
+        );
+        assert_eq!(
+            to_csv(&problems[..1]),
+            r#""lint","message","path"
+"track_gap","tracks 3 of `Nectar` are missing","a/1.mp3"
+"track_gap","tracks 3 of `Nectar` are missing","a/2.mp3"
+"track_gap","tracks 3 of `Nectar` are missing","a/4.mp3""#
+        );
+        assert_eq!(songs(problems).len(), 3);
+    }
+
+    fn song(path: &str, album: &str, disc: Option<&str>, track: Option<&str>) -> TagDetails {
+        TagDetails {
+            path: path.to_string(),
+            album: Some(album.to_string()),
+            album_artist: Some(String::from("Joji")),
+            disc: disc.map(String::from),
+            track: track.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    fn default_songs() -> Vec<TagDetails> {
+        let mut first = song("a/1.mp3", "Nectar", None, Some("1"));
+        first.year = Some(String::from("2020-09-25"));
+        let mut last = song("a/4.mp3", "Nectar", None, Some("4/4"));
+        last.year = Some(String::from("2021"));
+        last.album_artist = None;
+        vec![
+            first,
+            song("a/2.mp3", "Nectar", None, Some("2")),
+            last,
+            song("b/1.mp3", "Scorpion", Some("1/2"), Some("2")),
+            song("b/2.mp3", "Scorpion", Some("1/2"), Some("2")),
+            song("b/3.mp3", "Scorpion", Some("2/2"), Some("1")),
+            song("b/4.mp3", "Scorpion", Some("2/2"), None),
+            song("b/2.mp3", "Scorpion", Some("1/2"), Some("2")),
+            TagDetails {
+                path: "c/1.mp3".to_string(),
+                title: Some(String::from("Passionfruit ")),
+                genre: Some(String::from(" Hip-Hop")),
+                artist: Some(String::from("Drake")),
+                ..Default::default()
+            },
+        ]
+    }
+}
@@ -0,0 +1,245 @@
+use crate::tag::details::TagDetails;
+use crate::tag::r#type::TagType;
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashMap;
+
+/// Which copy of a song is kept when many share the same key
+#[derive(Debug, PartialEq)]
+pub enum Preference {
+    /// Songs of an album of their own artist over the ones of compilations
+    Album,
+    /// Songs with the highest average bitrate
+    Bitrate,
+    Older,
+    Newer,
+}
+
+/// Collapses the songs sharing the same values of the key tags (compared ignoring case)
+#[derive(Debug, PartialEq)]
+pub struct Distinct {
+    pub keys: Vec<TagType>,
+    pub preferences: Vec<Preference>,
+}
+
+impl Distinct {
+    /// Keeps one copy of each song where the first one was, the first copy being kept unless a
+    /// preference (checked in order) favours another. Songs missing a key tag are all kept
+    pub fn apply(&self, songs: Vec<TagDetails>) -> Vec<TagDetails> {
+        let mut positions = HashMap::<Vec<String>, usize>::new();
+        let mut kept = Vec::<TagDetails>::new();
+        for song in songs {
+            match self.key(&song) {
+                Some(key) => match positions.get(&key) {
+                    Some(&position) => {
+                        if self.compare(&song, &kept[position]) == Ordering::Greater {
+                            kept[position] = song;
+                        }
+                    }
+                    None => {
+                        positions.insert(key, kept.len());
+                        kept.push(song);
+                    }
+                },
+                None => kept.push(song),
+            }
+        }
+        kept
+    }
+
+    fn key(&self, song: &TagDetails) -> Option<Vec<String>> {
+        self.keys
+            .iter()
+            .map(|tag| {
+                tag.collect(song)
+                    .map(|value| value.trim().to_lowercase())
+                    .filter(|value| !value.is_empty())
+            })
+            .collect()
+    }
+
+    /// `Greater` when `song` is preferred over `other`
+    fn compare(&self, song: &TagDetails, other: &TagDetails) -> Ordering {
+        self.preferences
+            .iter()
+            .map(|preference| match preference {
+                Preference::Album => (!is_compilation(song)).cmp(&!is_compilation(other)),
+                Preference::Bitrate => bitrate(song).cmp(&bitrate(other)),
+                Preference::Older => song
+                    .year_number()
+                    .map(Reverse)
+                    .cmp(&other.year_number().map(Reverse)),
+                Preference::Newer => song.year_number().cmp(&other.year_number()),
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Songs of "Various Artists" albums or of albums by an artist not credited in the song
+fn is_compilation(song: &TagDetails) -> bool {
+    match song
+        .album_artist
+        .as_deref()
+        .map(|e| e.trim().to_lowercase())
+    {
+        Some(album_artist) if ["various artists", "various", "va"].contains(&&*album_artist) => {
+            true
+        }
+        Some(album_artist) if !album_artist.is_empty() => song
+            .artist
+            .as_deref()
+            .is_some_and(|artist| !artist.to_lowercase().contains(&album_artist)),
+        _ => false,
+    }
+}
+
+/// Average bitrate in kbps, from the size and duration of the song
+fn bitrate(song: &TagDetails) -> Option<u64> {
+    let duration = song.duration.filter(|duration| *duration > 0)?;
+    Some(song.size? * 8 / duration / 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::distinct::{bitrate, is_compilation, Distinct, Preference};
+    use crate::tag::details::TagDetails;
+    use crate::tag::r#type::TagType;
+
+    #[test]
+    fn ensure_fn_apply_works_as_expected_1() {
+        let distinct = Distinct {
+            keys: vec![TagType::Artist, TagType::Title],
+            preferences: vec![],
+        };
+
+        let kept = distinct.apply(default_songs());
+
+        assert_eq!(
+            paths(&kept),
+            vec!["hits/1.mp3", "album/2.mp3", "x/1.mp3", "x/2.mp3"]
+        );
+    }
+
+    #[test]
+    fn ensure_fn_apply_works_as_expected_2() {
+        let distinct = Distinct {
+            keys: vec![TagType::Artist, TagType::Title],
+            preferences: vec![Preference::Album],
+        };
+
+        let kept = distinct.apply(default_songs());
+
+        assert_eq!(
+            paths(&kept),
+            vec!["album/1.mp3", "album/2.mp3", "x/1.mp3", "x/2.mp3"]
+        );
+    }
+
+    #[test]
+    fn ensure_fn_apply_works_as_expected_3() {
+        let distinct = Distinct {
+            keys: vec![TagType::Title],
+            preferences: vec![Preference::Bitrate, Preference::Older],
+        };
+
+        let kept = distinct.apply(default_songs());
+
+        assert_eq!(
+            paths(&kept),
+            vec!["remaster/1.mp3", "album/2.mp3", "x/1.mp3", "x/2.mp3"]
+        );
+    }
+
+    #[test]
+    fn ensure_fn_apply_works_as_expected_4() {
+        let newer = Distinct {
+            keys: vec![TagType::Title],
+            preferences: vec![Preference::Newer],
+        };
+        let older = Distinct {
+            keys: vec![TagType::Title],
+            preferences: vec![Preference::Older],
+        };
+
+        assert_eq!(
+            paths(&newer.apply(default_songs())),
+            vec!["remaster/1.mp3", "album/2.mp3", "x/1.mp3", "x/2.mp3"]
+        );
+        assert_eq!(
+            paths(&older.apply(default_songs())),
+            vec!["album/1.mp3", "album/2.mp3", "x/1.mp3", "x/2.mp3"]
+        );
+    }
+
+    #[test]
+    fn ensure_fn_is_compilation_works_as_expected_1() {
+        let song = |artist: &str, album_artist: Option<&str>| TagDetails {
+            artist: Some(artist.to_string()),
+            album_artist: album_artist.map(String::from),
+            ..Default::default()
+        };
+
+        assert!(is_compilation(&song("Drake", Some("Various Artists"))));
+        assert!(is_compilation(&song("Drake", Some("DJ Khaled"))));
+        assert!(!is_compilation(&song("Drake feat. Rihanna", Some("Drake"))));
+        assert!(!is_compilation(&song("Drake", None)));
+    }
+
+    #[test]
+    fn ensure_fn_bitrate_works_as_expected_1() {
+        let song = TagDetails {
+            size: Some(8_000_000),
+            duration: Some(200),
+            ..Default::default()
+        };
+
+        assert_eq!(bitrate(&song), Some(320));
+        assert_eq!(bitrate(&TagDetails::default()), None);
+    }
+
+    fn paths(songs: &[TagDetails]) -> Vec<&str> {
+        songs.iter().map(|song| song.path.as_str()).collect()
+    }
+
+    fn default_songs() -> Vec<TagDetails> {
+        let song =
+            |path: &str, title: &str, album_artist: &str, year: &str, size: u64| TagDetails {
+                path: path.to_string(),
+                title: Some(title.to_string()),
+                artist: Some(String::from("Drake")),
+                album_artist: Some(album_artist.to_string()),
+                year: Some(year.to_string()),
+                duration: Some(200),
+                size: Some(size),
+                ..Default::default()
+            };
+        vec![
+            song(
+                "hits/1.mp3",
+                "Passionfruit",
+                "Various Artists",
+                "2019",
+                4_000_000,
+            ),
+            song("album/1.mp3", "Passionfruit", "Drake", "2017", 6_000_000),
+            song("album/2.mp3", "Madiba Riddim", "Drake", "2017", 6_000_000),
+            song(
+                "remaster/1.mp3",
+                "PASSIONFRUIT ",
+                "Drake",
+                "2022",
+                8_000_000,
+            ),
+            TagDetails {
+                path: "x/1.mp3".to_string(),
+                artist: Some(String::from("Drake")),
+                ..Default::default()
+            },
+            TagDetails {
+                path: "x/2.mp3".to_string(),
+                artist: Some(String::from("Drake")),
+                ..Default::default()
+            },
+        ]
+    }
+}
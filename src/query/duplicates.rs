@@ -0,0 +1,213 @@
+use crate::tag::audio::{hash_payload, payload};
+use crate::tag::details::TagDetails;
+use crate::utils;
+use rayon::prelude::*;
+use serde::Serializer;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::Path;
+
+/// Songs with byte-identical audio, whatever their tags
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Duplicates {
+    /// Blake3 hash of the audio
+    pub hash: String,
+    /// Size of the audio of each copy, in bytes
+    pub audio_size: u64,
+    /// Bytes of audio freed by keeping a single copy
+    pub reclaimable: u64,
+    /// Copies in path order, written as their paths
+    #[serde(serialize_with = "paths")]
+    pub songs: Vec<TagDetails>,
+}
+
+/// Groups of songs with the same audio, the ones freeing the most space first. Only songs with
+/// audio of the same size are hashed (in parallel), files that can't be read are left out
+pub fn find(songs: &[TagDetails]) -> Vec<Duplicates> {
+    let mut seen = HashSet::<&str>::new();
+    let mut by_size = HashMap::<u64, Vec<(&TagDetails, Range<u64>)>>::new();
+    for song in songs.iter().filter(|song| seen.insert(song.path.as_str())) {
+        if let Some(payload) = payload(Path::new(&song.path)) {
+            by_size
+                .entry(payload.end - payload.start)
+                .or_default()
+                .push((song, payload));
+        }
+    }
+
+    let candidates = by_size
+        .into_values()
+        .filter(|songs| songs.len() > 1)
+        .flatten()
+        .collect::<Vec<(&TagDetails, Range<u64>)>>();
+    let hashes = candidates
+        .par_iter()
+        .filter_map(|(song, payload)| {
+            let hash = hash_payload(Path::new(&song.path), payload)?;
+            Some((hash, payload.end - payload.start, *song))
+        })
+        .collect::<Vec<(String, u64, &TagDetails)>>();
+
+    let mut groups = HashMap::<String, (u64, Vec<TagDetails>)>::new();
+    for (hash, size, song) in hashes {
+        groups
+            .entry(hash)
+            .or_insert((size, vec![]))
+            .1
+            .push(song.to_owned());
+    }
+    let mut duplicates = groups
+        .into_iter()
+        .filter(|(_, (_, songs))| songs.len() > 1)
+        .map(|(hash, (audio_size, mut songs))| {
+            songs.sort_by(|song, other| song.path.cmp(&other.path));
+            Duplicates {
+                hash,
+                audio_size,
+                reclaimable: audio_size * (songs.len() as u64 - 1),
+                songs,
+            }
+        })
+        .collect::<Vec<Duplicates>>();
+    duplicates
+        .sort_by_cached_key(|group| (Reverse(group.reclaimable), group.songs[0].path.clone()));
+    duplicates
+}
+
+/// Songs of the groups, group after group
+pub fn songs(duplicates: Vec<Duplicates>) -> Vec<TagDetails> {
+    duplicates
+        .into_iter()
+        .flat_map(|group| group.songs)
+        .collect()
+}
+
+/// Each group with its copies below it, followed by the space that can be freed
+pub fn report(duplicates: &[Duplicates]) -> String {
+    let mut lines = duplicates
+        .iter()
+        .map(|group| {
+            let mut lines = vec![format!(
+                "{}  {} copies  {} bytes reclaimable",
+                &group.hash[..16],
+                group.songs.len(),
+                group.reclaimable
+            )];
+            lines.extend(group.songs.iter().map(|song| format!("  {}", song.path)));
+            lines.join("\n")
+        })
+        .collect::<Vec<String>>();
+    lines.push(format!(
+        "{} groups of duplicates, {} bytes reclaimable",
+        duplicates.len(),
+        duplicates
+            .iter()
+            .map(|group| group.reclaimable)
+            .sum::<u64>()
+    ));
+    lines.join("\n\n")
+}
+
+/// Csv with one copy per row and the hash of its group
+pub fn to_csv(duplicates: &[Duplicates]) -> String {
+    utils::csv::to_string(
+        &["hash", "audio_size", "path"],
+        duplicates.iter().flat_map(|group| {
+            group
+                .songs
+                .iter()
+                .map(|song| (&group.hash, group.audio_size, &song.path))
+        }),
+    )
+}
+
+fn paths<S: Serializer>(songs: &[TagDetails], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(songs.iter().map(|song| song.path.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::duplicates::{find, report, songs, to_csv, Duplicates};
+    use crate::tag::details::TagDetails;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn ensure_fn_find_works_as_expected_1() {
+        let dir = std::env::temp_dir().join("playlist-maker-find-duplicates-1");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let audio = [0xFF, 0xFB, 0x90, 0x00, 0x01, 0x02, 0x03, 0x04];
+        let other = [0xFF, 0xFB, 0x90, 0x00, 0x04, 0x03, 0x02, 0x01];
+        let tag = [b"ID3".as_slice(), &[3, 0, 0, 0, 0, 0, 2], b"ab"].concat();
+        let files = [
+            write_song(&dir, "c.mp3", &[&audio]),
+            write_song(&dir, "a.mp3", &[&tag, &audio]),
+            write_song(&dir, "b.mp3", &[&other]),
+            write_song(&dir, "d.mp3", &[&audio, b"TAG"]),
+            dir.join("missing.mp3"),
+        ];
+        let songs = files
+            .iter()
+            .map(|file| TagDetails {
+                path: file.to_string_lossy().to_string(),
+                ..Default::default()
+            })
+            .collect::<Vec<TagDetails>>();
+
+        let duplicates = find(&songs);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(duplicates.len(), 1);
+        let group = duplicates.first().unwrap();
+        assert_eq!(group.audio_size, 8);
+        assert_eq!(group.reclaimable, 8);
+        assert_eq!(
+            group
+                .songs
+                .iter()
+                .map(|song| Path::new(&song.path).file_name().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["a.mp3", "c.mp3"]
+        );
+    }
+
+    #[test]
+    fn ensure_fn_report_works_as_expected_1() {
+        let duplicates = vec![default_duplicates()];
+
+        assert_eq!(
+            report(&duplicates),
+            "0123456789abcdef  2 copies  100 bytes reclaimable\n  a.mp3\n  b.mp3\n\n\
+             1 groups of duplicates, 100 bytes reclaimable"
+        );
+        assert_eq!(
+            to_csv(&duplicates),
+            r#""hash","audio_size","path"
+"0123456789abcdef0123","100","a.mp3"
+"0123456789abcdef0123","100","b.mp3""#
+        );
+        assert_eq!(songs(duplicates).len(), 2);
+    }
+
+    fn default_duplicates() -> Duplicates {
+        Duplicates {
+            hash: String::from("0123456789abcdef0123"),
+            audio_size: 100,
+            reclaimable: 100,
+            songs: ["a.mp3", "b.mp3"]
+                .iter()
+                .map(|path| TagDetails {
+                    path: path.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    fn write_song(dir: &Path, name: &str, parts: &[&[u8]]) -> PathBuf {
+        let file = dir.join(name);
+        std::fs::write(&file, parts.concat()).unwrap();
+        file
+    }
+}
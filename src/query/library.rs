@@ -0,0 +1,179 @@
+use crate::query::batch;
+use crate::query::processor::{ExprParser, Rule};
+use crate::query::string_extractor::InnerStringExtractor;
+use pest::Parser;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::process::exit;
+
+/// Named query expressions, referenced in queries with `Query("<name>")`
+#[derive(Default)]
+pub struct QueryLibrary {
+    queries: HashMap<String, String>,
+}
+
+impl QueryLibrary {
+    /// Reads a library file, one `<name> = <expression>` per line. Empty lines and lines starting
+    /// with `#` are skipped
+    pub fn read(file: &Path) -> Self {
+        let content = fs::read_to_string(file).unwrap_or_else(|_| {
+            println!(
+                "query library `{}` does not exist or is invalid!",
+                file.display()
+            );
+            exit(2);
+        });
+        let queries = batch::parse(&content).unwrap_or_else(|line| {
+            println!(
+                "query library `{}` has an invalid line {} (expected `<name> = <expression>`)!",
+                file.display(),
+                line
+            );
+            exit(2);
+        });
+        QueryLibrary {
+            queries: queries
+                .into_iter()
+                .map(|named| (named.name, named.query))
+                .collect(),
+        }
+    }
+
+    /// Query with every saved query it references replaced by its expression (in parenthesis)
+    pub fn resolve(&self, query: &str) -> Result<String, String> {
+        self.expand(query, Rule::query, &mut Vec::new())
+    }
+
+    /// Expression of a saved query with every saved query it references replaced as in `resolve`
+    pub fn resolve_saved(&self, name: &str) -> Result<String, String> {
+        self.expand(
+            &format!("Query(\"{}\")", name),
+            Rule::query_expr,
+            &mut Vec::new(),
+        )
+    }
+
+    fn expand(&self, text: &str, rule: Rule, stack: &mut Vec<String>) -> Result<String, String> {
+        let pairs = ExprParser::parse(rule, text).map_err(|error| error.to_string())?;
+        if rule == Rule::query_expr && pairs.as_str() != text.trim() {
+            return Err(format!(
+                "saved query `{}` is not a valid expression!",
+                stack.last().map_or("", |name| name.as_str())
+            ));
+        }
+
+        let mut expanded = String::new();
+        let mut last = 0;
+        for pair in pairs.flatten().filter(|pair| pair.as_rule() == Rule::saved) {
+            let span = pair.as_span();
+            let name = pair.inner_str().unwrap_or_default();
+            if stack.contains(&name) {
+                return Err(format!(
+                    "saved query `{}` references itself ({} -> {})!",
+                    name,
+                    stack.join(" -> "),
+                    name
+                ));
+            }
+            let saved = self
+                .queries
+                .get(&name)
+                .ok_or_else(|| format!("saved query `{}` does not exist!", name))?;
+
+            stack.push(name);
+            let resolved = self.expand(saved, Rule::query_expr, stack)?;
+            stack.pop();
+
+            expanded.push_str(&text[last..span.start()]);
+            expanded.push('(');
+            expanded.push_str(&resolved);
+            expanded.push(')');
+            last = span.end();
+        }
+        expanded.push_str(&text[last..]);
+        Ok(expanded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::library::QueryLibrary;
+
+    #[test]
+    fn ensure_fn_resolve_works_as_expected_1() {
+        let library = default_library();
+
+        let query = library.resolve(r#"Play(Query("chill") & !InPlaylist("old"))"#);
+
+        assert_eq!(
+            query,
+            Ok(r#"Play((Genre("Jazz") | Genre("Lo-Fi")) & !InPlaylist("old"))"#.to_string())
+        );
+    }
+
+    #[test]
+    fn ensure_fn_resolve_works_as_expected_2() {
+        let library = default_library();
+
+        let query = library.resolve(r#"Play(Query('evening'))"#);
+
+        assert_eq!(
+            query,
+            Ok(r#"Play(((Genre("Jazz") | Genre("Lo-Fi")) & !Artist("Drake")))"#.to_string())
+        );
+    }
+
+    #[test]
+    fn ensure_fn_resolve_works_as_expected_3() {
+        let library = default_library();
+
+        assert_eq!(
+            library.resolve(r#"Play(Query("missing"))"#),
+            Err("saved query `missing` does not exist!".to_string())
+        );
+        assert_eq!(
+            library.resolve(r#"Play(Query("a"))"#),
+            Err("saved query `a` references itself (a -> b -> a)!".to_string())
+        );
+        assert_eq!(
+            library.resolve(r#"Play(Query("broken"))"#),
+            Err("saved query `broken` is not a valid expression!".to_string())
+        );
+    }
+
+    #[test]
+    fn ensure_fn_resolve_saved_works_as_expected_1() {
+        let library = default_library();
+
+        assert_eq!(
+            library.resolve_saved("evening"),
+            Ok(r#"((Genre("Jazz") | Genre("Lo-Fi")) & !Artist("Drake"))"#.to_string())
+        );
+        assert!(library.resolve_saved("missing").is_err());
+    }
+
+    #[test]
+    fn ensure_fn_resolve_works_as_expected_4() {
+        let library = QueryLibrary::default();
+
+        let query = library.resolve(r#"Play(Genre("Jazz"))"#);
+
+        assert_eq!(query, Ok(r#"Play(Genre("Jazz"))"#.to_string()));
+    }
+
+    fn default_library() -> QueryLibrary {
+        QueryLibrary {
+            queries: [
+                ("chill", r#"Genre("Jazz") | Genre("Lo-Fi")"#),
+                ("evening", r#"Query("chill") & !Artist("Drake")"#),
+                ("a", r#"Query("b")"#),
+                ("b", r#"Genre("Pop") | Query("a")"#),
+                ("broken", r#"Genre("Pop") &"#),
+            ]
+            .into_iter()
+            .map(|(name, query)| (name.to_string(), query.to_string()))
+            .collect(),
+        }
+    }
+}
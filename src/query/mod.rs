@@ -1,2 +1,9 @@
+pub mod albums;
+pub mod batch;
+pub mod check;
+pub mod distinct;
+pub mod duplicates;
+pub mod library;
 pub mod processor;
+pub mod stats;
 mod string_extractor;
@@ -1,12 +1,20 @@
+use std::collections::{BTreeSet, HashSet};
 use std::process::exit;
 
 use pest::iterators::{Pair, Pairs};
 use pest::Parser;
+use rayon::prelude::*;
 
+use crate::index::inverted::{Postings, SongId};
 use crate::playlist::Playlist;
+use crate::query::distinct::{Distinct, Preference};
+use crate::query::stats::Grouping;
 use crate::query::string_extractor::{InnerStringExtractor, RuleExtractor, StringExtractor};
 use crate::tag::checker::{SearchType, TagChecker};
 use crate::tag::details::TagDetails;
+use crate::tag::r#type::TagType;
+use crate::tag::writer::Assignment;
+use crate::utils::template::Template;
 
 #[derive(Parser)]
 #[grammar = "query/grammar.pest"] // relative to src
@@ -17,6 +25,20 @@ pub enum QueryType {
     #[default]
     Play,
     Index,
+    /// Counts and durations of the songs by group
+    Stats(Grouping),
+    /// Albums of the songs, only the complete ones if asked
+    Albums {
+        complete: bool,
+    },
+    /// Groups of songs with the same audio
+    Duplicates,
+    /// Problems found in the tags of the songs
+    Check,
+    /// Tags written to the songs
+    Set(Vec<Assignment>),
+    /// Songs moved to the paths given by the layout
+    Move(Template),
 }
 
 pub fn process(
@@ -24,18 +46,136 @@ pub fn process(
     playlists: &[Playlist],
     query: &str,
 ) -> Option<Vec<TagDetails>> {
-    filter_query_expr(songs, playlists, parse_query(query).skip(1).next()?)
+    filter_query_expr(songs, playlists, parse_query(query).nth(1)?)
+}
+
+/// Same as `process`, for the songs of an inverted index (in the order of the index): the query is
+/// answered with sets of song ids, tags with postings being looked up instead of checked song by
+/// song, and ids are only turned into songs at the end
+pub fn process_indexed(
+    songs: &[TagDetails],
+    playlists: &[Playlist],
+    postings: &Postings,
+    query: &str,
+) -> Option<Vec<TagDetails>> {
+    let indexed = Indexed {
+        songs,
+        playlists,
+        postings,
+    };
+    let ids = indexed.query_expr(parse_query(query).nth(1)?)?;
+    // an index can have the same song twice, which `process` never gets
+    let mut seen = HashSet::<&TagDetails>::new();
+    Some(
+        ids.into_iter()
+            .filter_map(|id| songs.get(id as usize))
+            .filter(|song| seen.insert(song))
+            .map(|song| song.to_owned())
+            .collect(),
+    )
+}
+
+/// Keeps one copy of the songs sharing the key of the `Distinct` modifier of the query, if any
+pub fn distinct(songs: Vec<TagDetails>, query: &str) -> Vec<TagDetails> {
+    match parse_query(query).find(|pair| pair.as_rule() == Rule::distinct) {
+        Some(pair) => build_distinct(pair).apply(songs),
+        None => songs,
+    }
+}
+
+fn build_distinct(distinct: Pair<Rule>) -> Distinct {
+    let mut keys = vec![];
+    let mut preferences = vec![];
+    for pair in distinct.into_inner() {
+        match pair.as_rule() {
+            Rule::distinct_key => keys.push(
+                TagType::try_from(&pair.as_str().to_lowercase(), &SearchType::Contains)
+                    .unwrap_or_else(|| {
+                        println!("songs can't be told apart by `{}`!", pair.as_str());
+                        exit(2);
+                    }),
+            ),
+            Rule::preference => preferences.push(match pair.inner_rule() {
+                Some(Rule::prefer_album) => Preference::Album,
+                Some(Rule::prefer_bitrate) => Preference::Bitrate,
+                Some(Rule::prefer_older) => Preference::Older,
+                Some(Rule::prefer_newer) => Preference::Newer,
+                _ => unreachable!(),
+            }),
+            _ => unreachable!(),
+        }
+    }
+    Distinct { keys, preferences }
+}
+
+fn build_assignment(assignment: Pair<Rule>) -> Assignment {
+    let mut pairs = assignment.into_inner();
+    let (tag, value) = (pairs.next_str(), pairs.next_str());
+    Assignment::try_from(&tag.unwrap_or_default(), &value.unwrap_or_default()).unwrap_or_else(
+        |error| {
+            println!("{}", error);
+            exit(2);
+        },
+    )
+}
+
+/// Sorts the songs as asked by the `SortBy` modifier of the query, songs that can't be sorted (e.g.
+/// not in the playlist) are left at the end in the order they were
+pub fn sort(songs: &mut [TagDetails], playlists: &[Playlist], query: &str) -> Option<()> {
+    let sort_by = parse_query(query).find(|pair| pair.as_rule() == Rule::sort_by)?;
+    let order = sort_by.into_inner().next()?.into_inner().next()?;
+
+    match order.as_rule() {
+        Rule::playlist_order => {
+            let playlist = find_playlist(playlists, &order.inner_str()?)?;
+            songs.sort_by_cached_key(|song| playlist.position(&song.path).unwrap_or(usize::MAX));
+        }
+        _ => unreachable!(),
+    }
+    Some(())
 }
 
 pub fn get_type(query: &str) -> QueryType {
-    match parse_query(query).next().unwrap().as_rule() {
+    let mut pairs = parse_query(query);
+    match pairs.next().unwrap().as_rule() {
         Rule::play => QueryType::Play,
         Rule::index => QueryType::Index,
+        Rule::stats => {
+            let group = pairs
+                .find(|pair| pair.as_rule() == Rule::group_by)
+                .and_then(|pair| pair.inner_str())
+                .unwrap_or_default();
+            QueryType::Stats(Grouping::try_from(&group).unwrap_or_else(|| {
+                println!("songs can't be grouped by `{}`!", group);
+                exit(2);
+            }))
+        }
+        Rule::albums => QueryType::Albums {
+            complete: pairs.any(|pair| pair.as_rule() == Rule::complete),
+        },
+        Rule::duplicates => QueryType::Duplicates,
+        Rule::check => QueryType::Check,
+        Rule::set => QueryType::Set(
+            pairs
+                .filter(|pair| pair.as_rule() == Rule::assignment)
+                .map(build_assignment)
+                .collect(),
+        ),
+        Rule::move_to => {
+            let layout = pairs
+                .find(|pair| pair.as_rule() == Rule::layout)
+                .map(|pair| pair.as_str().to_string())
+                .unwrap_or_default();
+            QueryType::Move(Template::parse(&layout).unwrap_or_else(|error| {
+                println!("{}", error);
+                exit(2);
+            }))
+        }
         _ => unreachable!(),
     }
 }
 
-fn parse_query(query: &str) -> Pairs<Rule> {
+fn parse_query(query: &str) -> Pairs<'_, Rule> {
     ExprParser::parse(Rule::query, query).unwrap_or_else(|error| {
         println!("{}", error);
         exit(2);
@@ -91,21 +231,154 @@ fn filter_token(
     let pair = token.into_inner().next()?;
     match pair.as_rule() {
         Rule::playlist => {
-            let first = pair.inner_str()?;
-            Some(
-                playlists
-                    .iter()
-                    .find(|&playlist| playlist.name == first)?
-                    .filter(vec),
-            )
+            let mut inner = pair.into_inner();
+            let playlist = find_playlist(playlists, &inner.next_str()?)?;
+            match inner.next() {
+                Some(first) => {
+                    let first = first.as_str().parse::<usize>().ok()?;
+                    Some(playlist.filter_by_position(vec, |position| position <= first))
+                }
+                None => Some(playlist.filter(vec)),
+            }
         }
+        Rule::position => filter_position(vec, playlists, pair),
+        Rule::saved => unresolved(pair),
         Rule::tag => filter_tag(vec, pair),
         Rule::rec_token => filter_query_expr(vec, playlists, pair.into_inner().next()?),
         _ => unreachable!(),
     }
 }
 
+fn filter_position(
+    vec: &[TagDetails],
+    playlists: &[Playlist],
+    position: Pair<Rule>,
+) -> Option<Vec<TagDetails>> {
+    let (playlist, predicate) = position_predicate(playlists, position)?;
+    Some(playlist.filter_by_position(vec, predicate))
+}
+
+/// Playlist of a `PlaylistPosition` token and whether a position in it is selected
+fn position_predicate<'a>(
+    playlists: &'a [Playlist],
+    position: Pair<Rule>,
+) -> Option<(&'a Playlist, impl Fn(usize) -> bool + Sync)> {
+    let mut pairs = position.into_inner();
+
+    let playlist = find_playlist(playlists, &pairs.next_str()?)?;
+    let relation = pairs.next()?.inner_rule()?;
+    let limit = pairs.next_str()?.parse::<usize>().ok()?;
+
+    Some((playlist, move |place| match relation {
+        Rule::le => place <= limit,
+        Rule::ge => place >= limit,
+        Rule::lt => place < limit,
+        Rule::gt => place > limit,
+        Rule::eq => place == limit,
+        _ => unreachable!(),
+    }))
+}
+
+/// Playlists with the songs of their saved queries selected, once for all the songs instead of for
+/// every chunk and every `InPlaylist` token. `select` runs a query over all the songs, playlists
+/// referenced by saved queries are selected before the ones referencing them (`check_references`
+/// makes sure they never reference themselves)
+pub fn select_saved(
+    playlists: &[Playlist],
+    select: impl Fn(&[Playlist], &str) -> Vec<TagDetails>,
+) -> Vec<Playlist> {
+    let mut selected = playlists.to_vec();
+    let mut pending = (0..selected.len())
+        .filter(|&i| !selected[i].queries.is_empty())
+        .collect::<Vec<usize>>();
+    while !pending.is_empty() {
+        let names = pending
+            .iter()
+            .map(|&i| selected[i].name.to_owned())
+            .collect::<HashSet<String>>();
+        let (ready, waiting): (Vec<usize>, Vec<usize>) = pending.into_iter().partition(|&i| {
+            selected[i]
+                .queries
+                .iter()
+                .flat_map(|query| playlist_names(query))
+                .all(|name| !names.contains(&name))
+        });
+        if ready.is_empty() {
+            break;
+        }
+        for i in ready {
+            let query = format!("Play(({}))", selected[i].queries.join(") | ("));
+            check_positions(&selected, &query);
+            selected[i].saved = select(&selected, &query)
+                .into_iter()
+                .map(|song| song.path)
+                .collect();
+        }
+        pending = waiting;
+    }
+    selected
+}
+
+/// Songs selected by saved queries have no position in their playlist, so positions can only be
+/// asked for playlists without saved queries
+pub fn check_positions(playlists: &[Playlist], query: &str) {
+    let names = parse_query(query)
+        .flatten()
+        .filter(|pair| match pair.as_rule() {
+            Rule::playlist => pair.clone().into_inner().nth(1).is_some(),
+            Rule::position | Rule::playlist_order => true,
+            _ => false,
+        })
+        .filter_map(|pair| pair.inner_str());
+    for name in names {
+        if find_playlist(playlists, &name).is_some_and(|playlist| !playlist.queries.is_empty()) {
+            println!(
+                "playlist `{}` has saved queries, the positions of its songs can't be used!",
+                name
+            );
+            exit(2);
+        }
+    }
+}
+
+/// Whether the whole text is a query expression
+pub fn is_query_expr(query_expr: &str) -> bool {
+    ExprParser::parse(Rule::query_expr, query_expr)
+        .is_ok_and(|pairs| pairs.as_str() == query_expr.trim())
+}
+
+/// Names of the playlists used in a query expression
+pub fn playlist_names(query_expr: &str) -> Vec<String> {
+    ExprParser::parse(Rule::query_expr, query_expr)
+        .map(|pairs| {
+            pairs
+                .flatten()
+                .filter(|pair| matches!(pair.as_rule(), Rule::playlist | Rule::position))
+                .filter_map(|pair| pair.inner_str())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Saved queries are replaced by their expression before a query is evaluated, one left means it
+/// was not taken from a query library
+fn unresolved(saved: Pair<Rule>) -> ! {
+    println!(
+        "saved query `{}` is used without a query library!",
+        saved.inner_str().unwrap_or_default()
+    );
+    exit(2);
+}
+
+fn find_playlist<'a>(playlists: &'a [Playlist], name: &str) -> Option<&'a Playlist> {
+    playlists.iter().find(|&playlist| playlist.name == name)
+}
+
 fn filter_tag(vec: &[TagDetails], tag: Pair<Rule>) -> Option<Vec<TagDetails>> {
+    build_checker(tag).map(|checker| checker.filter(vec))
+}
+
+fn build_checker(tag: Pair<Rule>) -> Option<TagChecker> {
     let pair = &mut tag.into_inner();
 
     let search_type = match pair.next()?.as_rule() {
@@ -118,11 +391,100 @@ fn filter_tag(vec: &[TagDetails], tag: Pair<Rule>) -> Option<Vec<TagDetails>> {
     let tag_type = pair.next_str()?;
     let metadata = pair.next_str()?;
 
-    TagChecker::try_from(metadata, tag_type, search_type).map(|checker| checker.filter(vec))
+    TagChecker::try_from(metadata, tag_type, search_type)
+}
+
+/// Songs of an inverted index, a query over them selects the ids (positions) of the songs
+struct Indexed<'a> {
+    songs: &'a [TagDetails],
+    playlists: &'a [Playlist],
+    postings: &'a Postings,
+}
+
+impl Indexed<'_> {
+    /// `&` intersects the ids selected by each side and `|` unites them
+    fn query_expr(&self, query_expr: Pair<Rule>) -> Option<BTreeSet<SongId>> {
+        let mut pairs = query_expr.into_inner();
+        let mut output = self.maybe_not_token(pairs.next()?)?;
+
+        while let Some(operator) = pairs.next() {
+            let ids = self.maybe_not_token(pairs.next()?)?;
+            match operator.inner_rule()? {
+                Rule::and => output.retain(|id| ids.contains(id)),
+                Rule::or => output.extend(ids),
+                _ => unreachable!(),
+            }
+        }
+        Some(output)
+    }
+
+    /// `!` selects the ids of every other song
+    fn maybe_not_token(&self, maybe_not_token: Pair<Rule>) -> Option<BTreeSet<SongId>> {
+        let mut pairs = maybe_not_token.into_inner();
+        let first = pairs.next()?;
+
+        match first.as_rule() {
+            Rule::not => {
+                let to_remove = self.token(pairs.next()?)?;
+                Some(
+                    (0..self.songs.len() as SongId)
+                        .filter(|id| !to_remove.contains(id))
+                        .collect(),
+                )
+            }
+            Rule::token => self.token(first),
+            _ => unreachable!(),
+        }
+    }
+
+    fn token(&self, token: Pair<Rule>) -> Option<BTreeSet<SongId>> {
+        let pair = token.into_inner().next()?;
+        match pair.as_rule() {
+            Rule::playlist => {
+                let mut inner = pair.into_inner();
+                let playlist = find_playlist(self.playlists, &inner.next_str()?)?;
+                match inner.next() {
+                    Some(first) => {
+                        let first = first.as_str().parse::<usize>().ok()?;
+                        Some(self.scan(|song| {
+                            playlist
+                                .position(&song.path)
+                                .is_some_and(|position| position <= first)
+                        }))
+                    }
+                    None => Some(self.scan(|song| playlist.contains(&song.path))),
+                }
+            }
+            Rule::position => {
+                let (playlist, predicate) = position_predicate(self.playlists, pair)?;
+                Some(self.scan(|song| playlist.position(&song.path).is_some_and(&predicate)))
+            }
+            Rule::saved => unresolved(pair),
+            Rule::tag => {
+                let checker = build_checker(pair)?;
+                checker
+                    .lookup(self.postings)
+                    .or_else(|| Some(self.scan(|song| checker.matches(song))))
+            }
+            Rule::rec_token => self.query_expr(pair.into_inner().next()?),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Ids of the songs matching `predicate`, checked song by song for what postings can't answer
+    fn scan(&self, predicate: impl Fn(&TagDetails) -> bool + Sync) -> BTreeSet<SongId> {
+        self.songs
+            .par_iter()
+            .enumerate()
+            .filter(|(_, song)| predicate(song))
+            .map(|(id, _)| id as SongId)
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::index::inverted::Postings;
     use crate::playlist::Playlist;
     use crate::query::processor::*;
     use crate::tag::details::TagDetails;
@@ -257,10 +619,11 @@ mod tests {
             .next()
             .unwrap();
         let songs = default_songs();
-        let playlist = Playlist {
-            name: "def".to_string(),
-            songs: vec!["test-data/songs/1.mp3".to_string()],
-        };
+        let playlist = Playlist::new(
+            "def".to_string(),
+            vec!["test-data/songs/1.mp3".to_string()],
+            false,
+        );
         let playlists = vec![playlist];
 
         let output = filter_token(songs.as_slice(), playlists.as_slice(), rule);
@@ -465,6 +828,211 @@ mod tests {
             .is_some());
     }
 
+    #[test]
+    fn ensure_fn_process_works_as_expected_2() {
+        let songs = default_songs();
+        let playlists = ranked_playlists();
+
+        let output = process(
+            songs.as_slice(),
+            playlists.as_slice(),
+            r#"Play(InPlaylist("top", first: 2) & !InPlaylist("last"))"#,
+        );
+
+        let selected = output.unwrap();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].path, "test-data/songs/4.mp3");
+    }
+
+    #[test]
+    fn ensure_fn_process_works_as_expected_3() {
+        let songs = default_songs();
+        let playlists = ranked_playlists();
+
+        let output = process(
+            songs.as_slice(),
+            playlists.as_slice(),
+            r#"Play(PlaylistPosition("top") > 1 | PlaylistPosition('last') = 1)"#,
+        );
+
+        let mut selected = output.unwrap();
+        selected.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(selected.len(), 3);
+        assert_eq!(selected[0].path, "test-data/songs/2.mp3");
+        assert_eq!(selected[1].path, "test-data/songs/4.mp3");
+        assert_eq!(selected[2].path, "test-data/songs/5.mp3");
+    }
+
+    #[test]
+    fn ensure_fn_process_works_as_expected_4() {
+        let songs = default_songs();
+        let mut playlists = ranked_playlists();
+        playlists[1].queries = vec![r#"Album("Orange") | InPlaylist("top", first: 1)"#.to_string()];
+        let playlists = select_saved(&playlists, |playlists, query| {
+            process(&songs, playlists, query).unwrap_or_default()
+        });
+
+        let output = process(
+            songs.as_slice(),
+            playlists.as_slice(),
+            r#"Play(InPlaylist("last"))"#,
+        );
+
+        let selected = output.unwrap();
+        assert_eq!(selected.len(), 2);
+        assert_eq!(selected[0].path, "test-data/songs/2.mp3");
+        assert_eq!(selected[1].path, "test-data/songs/4.mp3");
+    }
+
+    #[test]
+    fn ensure_fn_process_indexed_works_as_expected_1() {
+        let mut songs = default_songs();
+        songs.push(songs[0].to_owned());
+        let postings = Postings::new(&songs);
+        let mut playlists = ranked_playlists();
+        playlists[1].queries = vec![r#"Album("Orange") | InPlaylist("top", first: 1)"#.to_string()];
+        let playlists = select_saved(&playlists, |playlists, query| {
+            process_indexed(&songs, playlists, &postings, query).unwrap_or_default()
+        });
+
+        for query in [
+            r#"Play(C_Album("Black") | Artist("Cap"))"#,
+            r#"Play(!C_Album("Black") & !(Artist("Cap") | C_Path("3.mp3")))"#,
+            r#"Play(InPlaylist("top", first: 2) & !InPlaylist("last"))"#,
+            r#"Play(InPlaylist("last") | PlaylistPosition("top") > 2)"#,
+        ] {
+            let mut expected = process(&songs, &playlists, query).unwrap();
+            expected.sort_by(|a, b| a.path.cmp(&b.path));
+            expected.dedup();
+
+            let output = process_indexed(&songs, &playlists, &postings, query);
+
+            assert_eq!(output.unwrap(), expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn ensure_fn_select_saved_works_as_expected_1() {
+        let songs = default_songs();
+        let mut playlists = ranked_playlists();
+        playlists[0].queries = vec![r#"InPlaylist("last") & Album("Orange")"#.to_string()];
+        playlists[1].queries = vec![
+            r#"Album("Black")"#.to_string(),
+            r#"Album("Orange")"#.to_string(),
+        ];
+
+        let playlists = select_saved(&playlists, |playlists, query| {
+            process(&songs, playlists, query).unwrap_or_default()
+        });
+
+        assert_eq!(playlists[0].saved.len(), 1);
+        assert!(playlists[0].saved.contains("test-data/songs/4.mp3"));
+        assert_eq!(playlists[1].saved.len(), 2);
+    }
+
+    #[test]
+    fn ensure_fn_is_query_expr_works_as_expected_1() {
+        assert!(is_query_expr(
+            r#"Album("Orange") | InPlaylist("top", first: 1)"#
+        ));
+        assert!(!is_query_expr(r#"Album("Orange") |"#));
+        assert!(!is_query_expr(r#"Album("Orange") Genre("Pop")"#));
+    }
+
+    #[test]
+    fn ensure_fn_playlist_names_works_as_expected_1() {
+        let names = playlist_names(
+            r#"InPlaylist("a") & !(PlaylistPosition("b") < 3 | Genre("Pop")) | InPlaylist('c', first: 2)"#,
+        );
+
+        assert_eq!(names, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn ensure_fn_sort_works_as_expected_1() {
+        let mut songs = default_songs();
+        let playlists = ranked_playlists();
+
+        let output = sort(
+            songs.as_mut_slice(),
+            playlists.as_slice(),
+            r#"Play(C_Album("B"), SortBy(PlaylistOrder("top")))"#,
+        );
+
+        assert!(output.is_some());
+        let paths = songs.iter().map(|s| s.path.as_str()).collect::<Vec<&str>>();
+        assert_eq!(
+            paths,
+            vec![
+                "test-data/songs/2.mp3",
+                "test-data/songs/4.mp3",
+                "test-data/songs/5.mp3",
+                "test-data/songs/1.mp3",
+                "test-data/songs/3.mp3",
+            ]
+        );
+    }
+
+    #[test]
+    fn ensure_fn_sort_works_as_expected_2() {
+        let mut songs = default_songs();
+        let playlists = ranked_playlists();
+
+        let output = sort(
+            songs.as_mut_slice(),
+            playlists.as_slice(),
+            r#"Play(C_Album("B"))"#,
+        );
+
+        assert!(output.is_none());
+        assert_eq!(songs, default_songs());
+    }
+
+    #[test]
+    fn ensure_fn_distinct_works_as_expected_1() {
+        let mut songs = default_songs();
+        songs[1].album = Some(String::from("Black"));
+        songs[1].year = Some(String::from("2001"));
+
+        let output = distinct(
+            songs,
+            r#"Play(C_Album("B"), Distinct(Album, prefer: newer), SortBy(PlaylistOrder("x")))"#,
+        );
+
+        let paths = output
+            .iter()
+            .map(|song| song.path.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            paths,
+            vec![
+                "test-data/songs/2.mp3",
+                "test-data/songs/3.mp3",
+                "test-data/songs/4.mp3",
+                "test-data/songs/5.mp3"
+            ]
+        );
+    }
+
+    #[test]
+    fn ensure_fn_build_distinct_works_as_expected_1() {
+        let pair = ExprParser::parse(
+            Rule::distinct,
+            "Distinct(Artist, title, prefer: album, bitrate)",
+        )
+        .unwrap()
+        .next()
+        .unwrap();
+
+        assert_eq!(
+            build_distinct(pair),
+            Distinct {
+                keys: vec![TagType::Artist, TagType::Title],
+                preferences: vec![Preference::Album, Preference::Bitrate],
+            }
+        );
+    }
+
     #[test]
     fn ensure_fn_get_type_works_as_expected_1() {
         let output = get_type(r#"Play(Album("Black"))"#);
@@ -479,15 +1047,118 @@ mod tests {
         assert_eq!(output, QueryType::Index);
     }
 
+    #[test]
+    fn ensure_fn_get_type_works_as_expected_3() {
+        let output = get_type(r#"Stats(C_Genre("Jazz") | Year("2017"), group: Decade)"#);
+
+        assert_eq!(output, QueryType::Stats(Grouping::Decade));
+    }
+
+    #[test]
+    fn ensure_fn_get_type_works_as_expected_4() {
+        let output = get_type(r#"Albums(Genre("Rock") & AfterYear("2000"), complete)"#);
+
+        assert_eq!(output, QueryType::Albums { complete: true });
+        assert_eq!(
+            get_type(r#"Albums(Genre("Rock"))"#),
+            QueryType::Albums { complete: false }
+        );
+    }
+
+    #[test]
+    fn ensure_fn_get_type_works_as_expected_5() {
+        let output = get_type(r#"Duplicates(C_Artist("Drake"))"#);
+
+        assert_eq!(output, QueryType::Duplicates);
+    }
+
+    #[test]
+    fn ensure_fn_get_type_works_as_expected_6() {
+        let output = get_type(r#"Check(C_Artist("Drake") | C_Artist("Joji"))"#);
+
+        assert_eq!(output, QueryType::Check);
+    }
+
+    #[test]
+    fn ensure_fn_get_type_works_as_expected_7() {
+        let output = get_type(
+            r#"Set(C_Album("Jazz Hits"), Genre = "Jazz", AlbumArtist = 'Various Artists', Year = "")"#,
+        );
+
+        assert_eq!(
+            output,
+            QueryType::Set(vec![
+                Assignment::try_from("Genre", "Jazz").unwrap(),
+                Assignment::try_from("AlbumArtist", "Various Artists").unwrap(),
+                Assignment::try_from("Year", "").unwrap(),
+            ])
+        );
+    }
+
+    #[test]
+    fn ensure_fn_get_type_works_as_expected_8() {
+        let layout = "{albumartist}/{year} - {album}/{disc}-{track:02} {title}.{ext}";
+
+        let output = get_type(&format!(r#"Move(C_Artist("Joji"), "{}")"#, layout));
+
+        assert_eq!(output, QueryType::Move(Template::parse(layout).unwrap()));
+    }
+
+    #[test]
+    fn ensure_fn_get_type_works_as_expected_9() {
+        let double_quoted = "/music/{artist}'s/{title}.{ext}";
+        let single_quoted = "/music/\"{album}\"/{title}.{ext}";
+
+        let double_output = get_type(&format!(r#"Move(C_Artist("Joji"), "{}")"#, double_quoted));
+        let single_output = get_type(&format!(r#"Move(C_Artist("Joji"), '{}')"#, single_quoted));
+
+        assert_eq!(
+            double_output,
+            QueryType::Move(Template::parse(double_quoted).unwrap())
+        );
+        assert_eq!(
+            single_output,
+            QueryType::Move(Template::parse(single_quoted).unwrap())
+        );
+    }
+
+    #[test]
+    fn ensure_fn_process_works_as_expected_5() {
+        let songs = default_songs();
+
+        let output = process(&songs, &[], r#"Stats(C_Album("Black"), group: Genre)"#);
+
+        assert_eq!(output.map(|selected| selected.len()), Some(2));
+    }
+
     fn default_playlist() -> Vec<Playlist> {
-        let playlist = Playlist {
-            name: "def".to_string(),
-            songs: vec!["test-data/songs/1.mp3".to_string()],
-        };
+        let playlist = Playlist::new(
+            "def".to_string(),
+            vec!["test-data/songs/1.mp3".to_string()],
+            false,
+        );
         let playlists = vec![playlist];
         playlists
     }
 
+    fn ranked_playlists() -> Vec<Playlist> {
+        let top = Playlist::new(
+            "top".to_string(),
+            vec![
+                "test-data/songs/2.mp3".to_string(),
+                "test-data/songs/4.mp3".to_string(),
+                "test-data/songs/5.mp3".to_string(),
+            ],
+            false,
+        );
+        let last = Playlist::new(
+            "last".to_string(),
+            vec!["test-data/songs/2.mp3".to_string()],
+            false,
+        );
+        vec![top, last]
+    }
+
     fn default_songs() -> Vec<TagDetails> {
         let info1 = TagDetails {
             path: "test-data/songs/1.mp3".to_string(),
@@ -0,0 +1,268 @@
+use crate::tag::checker::SearchType;
+use crate::tag::details::TagDetails;
+use crate::tag::r#type::TagType;
+use crate::utils;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// What the songs of a `Stats` query are grouped by
+#[derive(Debug, Eq, PartialEq)]
+pub enum Grouping {
+    Decade,
+    Tag(TagType),
+}
+
+/// Songs sharing the same value of the grouping, `name` being `None` for songs without it
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Group {
+    pub name: Option<String>,
+    pub songs: usize,
+    /// Sum of the known durations, in seconds
+    pub duration: Option<u64>,
+}
+
+impl Grouping {
+    /// Decades or any tag that can be searched with `C_<tag>`, e.g. `Artist` or `Genre`
+    pub fn try_from(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "decade" => Some(Grouping::Decade),
+            name => TagType::try_from(name, &SearchType::Contains).map(Grouping::Tag),
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Grouping::Decade => String::from("Decade"),
+            Grouping::Tag(tag) => format!("{:?}", tag),
+        }
+    }
+
+    fn key(&self, song: &TagDetails) -> Option<String> {
+        match self {
+            Grouping::Decade => {
+                let year = song.year_number()?;
+                Some(format!("{}s", year - year % 10))
+            }
+            Grouping::Tag(tag) => tag
+                .collect(song)
+                .filter(|value| !value.is_empty())
+                .map(|value| value.to_string()),
+        }
+    }
+}
+
+/// Groups of the songs, decades in order and tags from the most common value, songs without the
+/// value always last
+pub fn group(songs: &[TagDetails], grouping: &Grouping) -> Vec<Group> {
+    let mut groups = HashMap::<Option<String>, Group>::new();
+    for song in songs {
+        let name = grouping.key(song);
+        let group = groups.entry(name.to_owned()).or_insert(Group {
+            name,
+            songs: 0,
+            duration: None,
+        });
+        group.songs += 1;
+        if let Some(duration) = song.duration {
+            group.duration = Some(group.duration.unwrap_or_default() + duration);
+        }
+    }
+
+    let mut groups = groups.into_values().collect::<Vec<Group>>();
+    match grouping {
+        Grouping::Decade => {
+            groups.sort_by_key(|group| (group.name.is_none(), group.name.to_owned()))
+        }
+        Grouping::Tag(_) => groups.sort_by_key(|group| {
+            (
+                Reverse(group.songs),
+                group.name.is_none(),
+                group.name.to_owned(),
+            )
+        }),
+    }
+    groups
+}
+
+/// Aligned table with a header, durations written as `h:mm:ss`
+pub fn table(grouping: &Grouping, groups: &[Group]) -> String {
+    let header = [
+        grouping.label(),
+        String::from("Songs"),
+        String::from("Duration"),
+    ];
+    let rows = groups
+        .iter()
+        .map(|group| {
+            [
+                group
+                    .name
+                    .to_owned()
+                    .unwrap_or_else(|| String::from("(none)")),
+                group.songs.to_string(),
+                group.duration.map_or_else(|| String::from("-"), clock),
+            ]
+        })
+        .collect::<Vec<[String; 3]>>();
+
+    let widths = (0..3)
+        .map(|column| {
+            rows.iter()
+                .chain([&header])
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<usize>>();
+    [&header]
+        .into_iter()
+        .chain(rows.iter())
+        .map(|row| {
+            format!(
+                "{:<name$}  {:>songs$}  {:>duration$}",
+                row[0],
+                row[1],
+                row[2],
+                name = widths[0],
+                songs = widths[1],
+                duration = widths[2]
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Csv with a `name`, `songs` and `duration` (in seconds) column
+pub fn to_csv(groups: &[Group]) -> String {
+    utils::csv::to_string(&["name", "songs", "duration"], groups)
+}
+
+fn clock(seconds: u64) -> String {
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::stats::{group, table, to_csv, Group, Grouping};
+    use crate::tag::details::TagDetails;
+    use crate::tag::r#type::TagType;
+
+    #[test]
+    fn ensure_fn_try_from_works_as_expected_1() {
+        assert_eq!(Grouping::try_from("Decade"), Some(Grouping::Decade));
+        assert_eq!(
+            Grouping::try_from("AlbumArtist"),
+            Some(Grouping::Tag(TagType::AlbumArtist))
+        );
+        assert_eq!(
+            Grouping::try_from("year"),
+            Some(Grouping::Tag(TagType::Date))
+        );
+        assert_eq!(Grouping::try_from("BeforeYear"), None);
+        assert_eq!(Grouping::try_from("Rating"), None);
+    }
+
+    #[test]
+    fn ensure_fn_group_works_as_expected_1() {
+        let groups = group(&default_songs(), &Grouping::Tag(TagType::Artist));
+
+        assert_eq!(
+            groups,
+            vec![
+                Group {
+                    name: Some(String::from("Drake")),
+                    songs: 2,
+                    duration: Some(450),
+                },
+                Group {
+                    name: Some(String::from("Joji")),
+                    songs: 1,
+                    duration: None,
+                },
+                Group {
+                    name: None,
+                    songs: 1,
+                    duration: Some(180),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn ensure_fn_group_works_as_expected_2() {
+        let groups = group(&default_songs(), &Grouping::Decade);
+
+        let names = groups
+            .iter()
+            .map(|group| (group.name.as_deref(), group.songs))
+            .collect::<Vec<(Option<&str>, usize)>>();
+        assert_eq!(
+            names,
+            vec![(Some("1990s"), 1), (Some("2010s"), 2), (None, 1)]
+        );
+    }
+
+    #[test]
+    fn ensure_fn_table_works_as_expected_1() {
+        let grouping = Grouping::Tag(TagType::Artist);
+
+        let output = table(&grouping, &group(&default_songs(), &grouping));
+
+        assert_eq!(
+            output,
+            "Artist  Songs  Duration\n\
+             Drake       2   0:07:30\n\
+             Joji        1         -\n\
+             (none)      1   0:03:00"
+        );
+    }
+
+    #[test]
+    fn ensure_fn_to_csv_works_as_expected_1() {
+        let output = to_csv(&group(&default_songs(), &Grouping::Decade));
+
+        assert_eq!(
+            output,
+            r#""name","songs","duration"
+"1990s","1","180"
+"2010s","2","450"
+"","1","""#
+        );
+    }
+
+    fn default_songs() -> Vec<TagDetails> {
+        vec![
+            TagDetails {
+                path: "test-data/songs/1.mp3".to_string(),
+                artist: Some(String::from("Drake")),
+                year: Some(String::from("2017")),
+                duration: Some(215),
+                ..Default::default()
+            },
+            TagDetails {
+                path: "test-data/songs/2.mp3".to_string(),
+                artist: Some(String::from("Joji")),
+                ..Default::default()
+            },
+            TagDetails {
+                path: "test-data/songs/3.mp3".to_string(),
+                artist: Some(String::from("Drake")),
+                year: Some(String::from("2016-04-29")),
+                duration: Some(235),
+                ..Default::default()
+            },
+            TagDetails {
+                path: "test-data/songs/4.mp3".to_string(),
+                artist: Some(String::new()),
+                year: Some(String::from("1998")),
+                duration: Some(180),
+                ..Default::default()
+            },
+        ]
+    }
+}
@@ -0,0 +1,332 @@
+use id3::Tag;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+/// Bytes searched, after the id3 tag, for the first MPEG audio frame
+const SEARCH_LIMIT: u64 = 64 * 1024;
+
+/// Bitrates (kbps) by bitrate index for MPEG1 layers 1, 2 and 3 and MPEG2/2.5 layers 1 and 2/3
+const BITRATES: [[u32; 16]; 5] = [
+    [
+        0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448, 0,
+    ],
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 0,
+    ],
+    [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 0,
+    ],
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256, 0,
+    ],
+    [
+        0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160, 0,
+    ],
+];
+
+/// Sample rates (Hz) by sample rate index for MPEG1, MPEG2 and MPEG2.5
+const SAMPLE_RATES: [[u32; 3]; 3] = [
+    [44100, 48000, 32000],
+    [22050, 24000, 16000],
+    [11025, 12000, 8000],
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum MpegVersion {
+    Mpeg1,
+    Mpeg2,
+    Mpeg25,
+}
+
+struct FrameHeader {
+    version: MpegVersion,
+    layer: u8,
+    bitrate: u32,
+    sample_rate: u32,
+    mono: bool,
+}
+
+impl FrameHeader {
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let header = u32::from_be_bytes(bytes.get(..4)?.try_into().ok()?);
+        if header >> 21 != 0x7FF {
+            return None;
+        }
+        let version = match (header >> 19) & 0b11 {
+            0b00 => MpegVersion::Mpeg25,
+            0b10 => MpegVersion::Mpeg2,
+            0b11 => MpegVersion::Mpeg1,
+            _ => return None,
+        };
+        let layer = match (header >> 17) & 0b11 {
+            0b01 => 3,
+            0b10 => 2,
+            0b11 => 1,
+            _ => return None,
+        };
+        let table = match (version, layer) {
+            (MpegVersion::Mpeg1, layer) => layer as usize - 1,
+            (_, 1) => 3,
+            _ => 4,
+        };
+        let bitrate = BITRATES[table][((header >> 12) & 0b1111) as usize];
+        let sample_rate = *SAMPLE_RATES[version as usize].get(((header >> 10) & 0b11) as usize)?;
+        if bitrate == 0 {
+            return None;
+        }
+        Some(FrameHeader {
+            version,
+            layer,
+            bitrate,
+            sample_rate,
+            mono: (header >> 6) & 0b11 == 0b11,
+        })
+    }
+
+    fn samples_per_frame(&self) -> u64 {
+        match (self.layer, self.version) {
+            (1, _) => 384,
+            (3, MpegVersion::Mpeg2 | MpegVersion::Mpeg25) => 576,
+            _ => 1152,
+        }
+    }
+
+    /// Number of frames announced by a Xing/Info or VBRI header, present in VBR files
+    fn frames(&self, frame: &[u8]) -> Option<u64> {
+        let side_info = match (self.version, self.mono) {
+            (MpegVersion::Mpeg1, false) => 32,
+            (MpegVersion::Mpeg1, true) | (_, false) => 17,
+            (_, true) => 9,
+        };
+        let xing = frame.get(4 + side_info..)?;
+        if xing.starts_with(b"Xing") || xing.starts_with(b"Info") {
+            let flags = u32::from_be_bytes(xing.get(4..8)?.try_into().ok()?);
+            return if flags & 1 == 1 {
+                Some(u32::from_be_bytes(xing.get(8..12)?.try_into().ok()?) as u64)
+            } else {
+                None
+            };
+        }
+        let vbri = frame.get(36..)?;
+        if vbri.starts_with(b"VBRI") {
+            return Some(u32::from_be_bytes(vbri.get(14..18)?.try_into().ok()?) as u64);
+        }
+        None
+    }
+}
+
+/// Duration in seconds of an mp3 file, estimated from its first MPEG audio frame: exact when the
+/// file has a Xing/VBRI header (VBR files) and computed from the bitrate otherwise
+pub fn read_duration(path: &Path) -> Option<u64> {
+    let mut file = File::open(path).ok()?;
+    let length = file.metadata().ok()?.len();
+    Tag::skip(&mut file).ok()?;
+    let start = file.stream_position().ok()?;
+
+    let mut bytes = Vec::new();
+    file.take(SEARCH_LIMIT).read_to_end(&mut bytes).ok()?;
+    // a tag announcing more bytes than the file has (e.g. a truncated file) leaves no audio
+    parse(&bytes, length.checked_sub(start)?)
+}
+
+/// Bytes of an mp3 file holding the audio, leaving out the id3v2 tag at the start and the id3v1
+/// and APEv2 tags at the end
+pub fn payload(path: &Path) -> Option<Range<u64>> {
+    let mut file = File::open(path).ok()?;
+    let mut end = file.metadata().ok()?.len();
+    Tag::skip(&mut file).ok()?;
+    let start = file.stream_position().ok()?;
+    // truncated files can have a tag running past their end
+    if start > end {
+        return None;
+    }
+
+    let mut trailer = [0; 128];
+    if end >= start + 128 {
+        file.seek(SeekFrom::Start(end - 128)).ok()?;
+        file.read_exact(&mut trailer).ok()?;
+        if trailer.starts_with(b"TAG") {
+            end -= 128;
+        }
+    }
+    let mut footer = [0; 32];
+    if end >= start + 32 {
+        file.seek(SeekFrom::Start(end - 32)).ok()?;
+        file.read_exact(&mut footer).ok()?;
+        if footer.starts_with(b"APETAGEX") {
+            // the size includes the footer but not the header, present when the top flag is set
+            let size = u32::from_le_bytes(footer[12..16].try_into().ok()?) as u64;
+            let header = if footer[23] & 0x80 != 0 { 32 } else { 0 };
+            end = end.saturating_sub(size + header).max(start);
+        }
+    }
+    Some(start..end)
+}
+
+/// Blake3 hash of the audio of an mp3 file, found with `payload`, so copies of a song tagged
+/// differently have the same hash
+pub fn hash_payload(path: &Path, payload: &Range<u64>) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    file.seek(SeekFrom::Start(payload.start)).ok()?;
+    let mut hasher = blake3::Hasher::new();
+    std::io::copy(&mut file.take(payload.end - payload.start), &mut hasher).ok()?;
+    Some(hasher.finalize().to_hex().to_string())
+}
+
+/// Version of the id3 tag of a file, `2.<major>` for id3v2 tags and `1` for files with only an
+/// id3v1 tag
+pub fn id3_version(path: &Path) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    let mut header = [0; 10];
+    if file.read_exact(&mut header).is_ok() && header.starts_with(b"ID3") {
+        return Some(format!("2.{}", header[3]));
+    }
+    let length = file.metadata().ok()?.len();
+    let mut trailer = [0; 3];
+    if length >= 128 {
+        file.seek(SeekFrom::Start(length - 128)).ok()?;
+        file.read_exact(&mut trailer).ok()?;
+    }
+    trailer.starts_with(b"TAG").then(|| String::from("1"))
+}
+
+/// Finds the first frame in `bytes`, the start of the audio data, `length` being the size of the
+/// whole audio data
+fn parse(bytes: &[u8], length: u64) -> Option<u64> {
+    let (offset, header) = (0..bytes.len())
+        .find_map(|offset| Some((offset, FrameHeader::parse(&bytes[offset..])?)))?;
+
+    match header.frames(&bytes[offset..]) {
+        Some(frames) if frames > 0 => {
+            Some(frames * header.samples_per_frame() / header.sample_rate as u64)
+        }
+        _ => Some((length - offset as u64) * 8 / (header.bitrate as u64 * 1000)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tag::audio::{hash_payload, id3_version, parse, payload, read_duration};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn ensure_fn_parse_works_as_expected_1() {
+        // MPEG1 layer 3, 128 kbps, 44100 Hz, stereo, after some garbage
+        let bytes = [0x00, 0x12, 0xFF, 0xFB, 0x90, 0x00, 0x00, 0x00];
+
+        assert_eq!(parse(&bytes, 2 + 16_000 * 180), Some(180));
+    }
+
+    #[test]
+    fn ensure_fn_parse_works_as_expected_2() {
+        // MPEG1 layer 3, 128 kbps, 44100 Hz, stereo with a Xing header announcing 6891 frames
+        let mut bytes = vec![0xFF, 0xFB, 0x90, 0x00];
+        bytes.extend_from_slice(&[0; 32]);
+        bytes.extend_from_slice(b"Xing");
+        bytes.extend_from_slice(&1u32.to_be_bytes());
+        bytes.extend_from_slice(&6891u32.to_be_bytes());
+
+        assert_eq!(parse(&bytes, 2_880_000), Some(180));
+    }
+
+    #[test]
+    fn ensure_fn_parse_works_as_expected_3() {
+        let bytes = [0x49, 0x44, 0x33, 0x04, 0x00];
+
+        assert_eq!(parse(&bytes, 5), None);
+    }
+
+    #[test]
+    fn ensure_fn_read_duration_works_as_expected_1() {
+        assert_eq!(read_duration(Path::new("test-data/songs/none.mp3")), None);
+        assert_eq!(
+            read_duration(Path::new("test-data/songs/id3v2.4.mp3")),
+            None
+        );
+    }
+
+    #[test]
+    fn ensure_fn_read_duration_works_as_expected_2() {
+        // id3v2 tag announcing 1000 bytes in a file cut right after its first audio frame header
+        let id3v2 = [b"ID3".as_slice(), &[3, 0, 0, 0, 0, 0x07, 0x68]].concat();
+        let truncated = write_song(
+            "playlist-maker-read-duration-2.mp3",
+            &[&id3v2, &[0xFF, 0xFB, 0x90, 0x00]],
+        );
+        let duration = read_duration(&truncated);
+        std::fs::remove_file(&truncated).unwrap();
+
+        assert_eq!(duration, None);
+    }
+
+    #[test]
+    fn ensure_fn_payload_works_as_expected_1() {
+        let audio = [0xFF, 0xFB, 0x90, 0x00, 0x01, 0x02, 0x03];
+        let id3v2 = [b"ID3".as_slice(), &[3, 0, 0, 0, 0, 0, 4], b"abcd"].concat();
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, b' ');
+        let mut ape = b"APETAGEX".to_vec();
+        ape.extend_from_slice(&2000u32.to_le_bytes());
+        ape.extend_from_slice(&41u32.to_le_bytes());
+        ape.extend_from_slice(&[0; 4]);
+        ape.extend_from_slice(&0x8000_0000u32.to_le_bytes());
+        ape.extend_from_slice(&[0; 8]);
+        let mut ape_tag = ape.clone();
+        ape_tag.extend_from_slice(b"ape items");
+        ape_tag.extend_from_slice(&ape);
+
+        let plain = write_song("playlist-maker-payload-1.mp3", &[&audio]);
+        let tagged = write_song(
+            "playlist-maker-payload-2.mp3",
+            &[&id3v2, &audio, &ape_tag, &id3v1],
+        );
+        let plain_payload = payload(&plain).unwrap();
+        let tagged_payload = payload(&tagged).unwrap();
+        let plain_hash = hash_payload(&plain, &plain_payload);
+        let tagged_hash = hash_payload(&tagged, &tagged_payload);
+        std::fs::remove_file(&plain).unwrap();
+        std::fs::remove_file(&tagged).unwrap();
+
+        assert_eq!(plain_payload, 0..7);
+        assert_eq!(tagged_payload, 14..21);
+        assert!(plain_hash.is_some());
+        assert_eq!(plain_hash, tagged_hash);
+    }
+
+    #[test]
+    fn ensure_fn_payload_works_as_expected_2() {
+        let id3v2 = [b"ID3".as_slice(), &[3, 0, 0, 0, 0, 0x07, 0x68]].concat();
+        let truncated = write_song(
+            "playlist-maker-payload-3.mp3",
+            &[&id3v2, &[0xFF, 0xFB, 0x90, 0x00]],
+        );
+        let payload = payload(&truncated);
+        std::fs::remove_file(&truncated).unwrap();
+
+        assert_eq!(payload, None);
+    }
+
+    #[test]
+    fn ensure_fn_id3_version_works_as_expected_1() {
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, b' ');
+        let old = write_song("playlist-maker-id3-version-1.mp3", &[&[0xFF, 0xFB], &id3v1]);
+        let version = id3_version(&old);
+        std::fs::remove_file(&old).unwrap();
+
+        assert_eq!(
+            id3_version(Path::new("test-data/songs/id3v2.4.mp3")).as_deref(),
+            Some("2.4")
+        );
+        assert_eq!(version.as_deref(), Some("1"));
+        assert_eq!(id3_version(Path::new("test-data/songs/none.mp3")), None);
+    }
+
+    fn write_song(name: &str, parts: &[&[u8]]) -> PathBuf {
+        let file = std::env::temp_dir().join(name);
+        std::fs::write(&file, parts.concat()).unwrap();
+        file
+    }
+}
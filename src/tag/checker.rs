@@ -1,7 +1,9 @@
+use crate::index::inverted::{Postings, SongId};
 use crate::tag::details::TagDetails;
 use crate::tag::matcher::TagMatcher;
 use crate::tag::r#type::TagType;
 use regex::Regex;
+use std::collections::BTreeSet;
 
 #[derive(PartialEq)]
 pub enum SearchType {
@@ -42,18 +44,40 @@ impl TagChecker {
 
     pub fn filter(&self, vec: &[TagDetails]) -> Vec<TagDetails> {
         vec.iter()
-            .filter(|info| {
-                self.tag
-                    .collect(info)
-                    .map_or(false, |info| self.matcher.matches(info))
-            })
+            .filter(|info| self.matches(info))
             .map(|song| song.to_owned())
             .collect::<Vec<TagDetails>>()
     }
+
+    pub fn matches(&self, song: &TagDetails) -> bool {
+        self.tag
+            .collect(song)
+            .is_some_and(|info| self.matcher.matches(info))
+    }
+
+    /// Ids of the songs that match, found by checking each distinct tag value once instead of the
+    /// tag of every song. `None` when the tag has no postings
+    pub fn lookup(&self, postings: &Postings) -> Option<BTreeSet<SongId>> {
+        let values = postings.values(&self.tag)?;
+        Some(match &self.matcher {
+            TagMatcher::Literal(metadata) => values
+                .get(metadata)
+                .into_iter()
+                .flatten()
+                .copied()
+                .collect(),
+            matcher => values
+                .iter()
+                .filter(|(value, _)| matcher.matches(value))
+                .flat_map(|(_, ids)| ids.iter().copied())
+                .collect(),
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::index::inverted::Postings;
     use crate::tag::checker::{SearchType, TagChecker};
     use crate::tag::details::TagDetails;
 
@@ -224,4 +248,70 @@ mod tests {
         let selected = output.unwrap().filter(vec![info].as_slice());
         assert_eq!(selected.len(), 0);
     }
+
+    #[test]
+    fn ensure_fn_lookup_works_as_expected_1() {
+        let songs = default_songs();
+        let postings = Postings::new(&songs);
+        let checker = TagChecker::try_from(
+            String::from("Camila"),
+            String::from("artist"),
+            SearchType::Contains,
+        )
+        .unwrap();
+
+        let ids = checker.lookup(&postings).unwrap();
+
+        assert_eq!(ids.into_iter().collect::<Vec<u32>>(), vec![0, 1]);
+    }
+
+    #[test]
+    fn ensure_fn_lookup_works_as_expected_2() {
+        let songs = default_songs();
+        let postings = Postings::new(&songs);
+        let before = TagChecker::try_from(
+            String::from("2018"),
+            String::from("beforeyear"),
+            SearchType::Literal,
+        )
+        .unwrap();
+        let path = TagChecker::try_from(
+            String::from("1.mp3"),
+            String::from("path"),
+            SearchType::Contains,
+        )
+        .unwrap();
+
+        let ids = before.lookup(&postings).unwrap();
+
+        assert_eq!(
+            ids.iter()
+                .map(|id| songs[*id as usize].to_owned())
+                .collect::<Vec<TagDetails>>(),
+            before.filter(&songs)
+        );
+        assert!(path.lookup(&postings).is_none());
+    }
+
+    fn default_songs() -> Vec<TagDetails> {
+        let info1 = TagDetails {
+            path: "test-data/songs/1.mp3".to_string(),
+            artist: Some("Camila Cabello".to_string()),
+            year: Some("2018".to_string()),
+            ..Default::default()
+        };
+        let info2 = TagDetails {
+            path: "test-data/songs/2.mp3".to_string(),
+            artist: Some("Camila Cabello".to_string()),
+            year: Some("2017".to_string()),
+            ..Default::default()
+        };
+        let info3 = TagDetails {
+            path: "test-data/songs/3.mp3".to_string(),
+            artist: Some("Drake".to_string()),
+            year: Some("2019".to_string()),
+            ..Default::default()
+        };
+        vec![info1, info2, info3]
+    }
 }
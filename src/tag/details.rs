@@ -1,10 +1,11 @@
+use crate::tag::audio::read_duration;
 use id3::{Tag, TagLike};
-use std::fmt;
-use std::fmt::{Display, Formatter};
+use std::fs;
 use std::hash::{Hash, Hasher};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 
-#[derive(Clone, Eq, Default, Debug, Deserialize)]
+#[derive(Clone, Eq, Default, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct TagDetails {
     pub path: String,
@@ -16,31 +17,85 @@ pub struct TagDetails {
     pub year: Option<String>,
     pub genre: Option<String>,
     pub disc: Option<String>,
+    pub mb_recording_id: Option<String>,
+    pub mb_release_id: Option<String>,
+    pub mb_release_group_id: Option<String>,
+    pub mb_artist_id: Option<String>,
+    /// Duration in seconds
+    pub duration: Option<u64>,
+    pub modified: Option<u64>,
+    pub size: Option<u64>,
 }
 
 impl TagDetails {
-    pub fn headers() -> String {
-        String::from(
-            r#""path","track","title","artist","album","album_artist","year","genre","disc""#,
-        )
+    /// Names of the fields, in the order they are declared (and written to csv indexes)
+    pub const FIELDS: [&'static str; 16] = [
+        "path",
+        "track",
+        "title",
+        "artist",
+        "album",
+        "album_artist",
+        "year",
+        "genre",
+        "disc",
+        "mb_recording_id",
+        "mb_release_id",
+        "mb_release_group_id",
+        "mb_artist_id",
+        "duration",
+        "modified",
+        "size",
+    ];
+
+    /// Value of the field named `name` as text, `None` if the song doesn't have it
+    pub fn field(&self, name: &str) -> Option<String> {
+        match name {
+            "path" => Some(self.path.to_owned()),
+            "track" => self.track.to_owned(),
+            "title" => self.title.to_owned(),
+            "artist" => self.artist.to_owned(),
+            "album" => self.album.to_owned(),
+            "album_artist" => self.album_artist.to_owned(),
+            "year" => self.year.to_owned(),
+            "genre" => self.genre.to_owned(),
+            "disc" => self.disc.to_owned(),
+            "mb_recording_id" => self.mb_recording_id.to_owned(),
+            "mb_release_id" => self.mb_release_id.to_owned(),
+            "mb_release_group_id" => self.mb_release_group_id.to_owned(),
+            "mb_artist_id" => self.mb_artist_id.to_owned(),
+            "duration" => self.duration.map(|e| e.to_string()),
+            "modified" => self.modified.map(|e| e.to_string()),
+            "size" => self.size.map(|e| e.to_string()),
+            // not stored, the extension of the file
+            "ext" => Path::new(&self.path)
+                .extension()
+                .map(|e| e.to_string_lossy().to_string()),
+            _ => None,
+        }
+    }
+
+    /// Year of the song as a number, ignoring the rest of full dates (e.g. `2016-04-29`)
+    pub fn year_number(&self) -> Option<u32> {
+        self.year
+            .as_deref()?
+            .trim()
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect::<String>()
+            .parse()
+            .ok()
     }
-}
 
-impl Display for TagDetails {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        let rev = [
-            self.path.as_str(),
-            self.track.as_deref().unwrap_or(""),
-            self.title.as_deref().unwrap_or(""),
-            self.artist.as_deref().unwrap_or(""),
-            self.album.as_deref().unwrap_or(""),
-            self.album_artist.as_deref().unwrap_or(""),
-            self.year.as_deref().unwrap_or(""),
-            self.genre.as_deref().unwrap_or(""),
-            self.disc.as_deref().unwrap_or(""),
-        ]
-        .join(r#"",""#);
-        write!(f, "\"{}\"", rev)
+    /// Name shown by players for a song: `<artist> - <title>`, falling back to the file name
+    pub fn display_name(&self) -> String {
+        match (self.artist.as_deref(), self.title.as_deref()) {
+            (Some(artist), Some(title)) => format!("{} - {}", artist, title),
+            (None, Some(title)) => title.to_string(),
+            _ => Path::new(&self.path)
+                .file_stem()
+                .map_or_else(|| self.path.clone(), |e| e.to_string_lossy().to_string()),
+        }
     }
 }
 
@@ -48,6 +103,7 @@ impl TryFrom<&PathBuf> for TagDetails {
     type Error = id3::Error;
 
     fn try_from(value: &PathBuf) -> Result<Self, Self::Error> {
+        let (modified, size) = file_stamp(value).unzip();
         Tag::read_from_path(value).map(|tag| {
             TagDetails {
                 path: value.to_string_lossy().to_string(),
@@ -66,11 +122,59 @@ impl TryFrom<&PathBuf> for TagDetails {
                     None => tag.date_recorded().map(|t| t.year),
                 }
                 .map(|e| e.to_string()),
+                mb_recording_id: musicbrainz_recording_id(&tag),
+                mb_release_id: extended_text(&tag, "MusicBrainz Album Id"),
+                mb_release_group_id: extended_text(&tag, "MusicBrainz Release Group Id"),
+                mb_artist_id: extended_text(&tag, "MusicBrainz Artist Id"),
+                // TLEN holds the length in milliseconds, most taggers don't write it though
+                duration: tag
+                    .duration()
+                    .map(|e| e as u64 / 1000)
+                    .or_else(|| read_duration(value)),
+                modified,
+                size,
             }
         })
     }
 }
 
+// MusicBrainz Picard stores the recording id in an UFID frame owned by "http://musicbrainz.org",
+// the id3 crate has no dedicated content for it so the raw frame is split here: the owner is
+// null terminated and the identifier is the rest of the frame
+fn musicbrainz_recording_id(tag: &Tag) -> Option<String> {
+    tag.frames()
+        .filter(|frame| frame.id() == "UFID")
+        .filter_map(|frame| frame.content().to_unknown().ok())
+        .find_map(|unknown| {
+            let mut parts = unknown.data.splitn(2, |byte| *byte == 0);
+            match (parts.next(), parts.next()) {
+                (Some(b"http://musicbrainz.org"), Some(id)) => {
+                    Some(String::from_utf8_lossy(id).to_string())
+                }
+                _ => None,
+            }
+        })
+}
+
+/// Modification time (seconds since the unix epoch) and size of a file, used to know if an
+/// indexed song needs to be read again
+pub fn file_stamp(path: &Path) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    Some((modified, metadata.len()))
+}
+
+fn extended_text(tag: &Tag, description: &str) -> Option<String> {
+    tag.extended_texts()
+        .find(|text| text.description.eq_ignore_ascii_case(description))
+        .map(|text| text.value.to_string())
+}
+
 impl Hash for TagDetails {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.path.hash(state);
@@ -85,12 +189,17 @@ impl PartialEq for TagDetails {
 
 #[cfg(test)]
 mod tests {
+    use crate::index::csv_rows;
     use crate::tag::details::TagDetails;
+    use id3::frame::{Content, ExtendedText, Unknown};
+    use id3::{Frame, Tag, TagLike, Version};
     use std::path::PathBuf;
 
+    const HEADER: &str = r#""path","track","title","artist","album","album_artist","year","genre","disc","mb_recording_id","mb_release_id","mb_release_group_id","mb_artist_id","duration","modified","size""#;
+
     #[test]
     fn tag_details_prints_headers_correctly() {
-        assert_eq!("\"path\",\"track\",\"title\",\"artist\",\"album\",\"album_artist\",\"year\",\"genre\",\"disc\"", TagDetails::headers());
+        assert_eq!(HEADER, csv_rows(&[]));
     }
 
     #[test]
@@ -105,21 +214,19 @@ mod tests {
             genre: Some(String::from("Rap")),
             disc: Some(String::from("1")),
             track: Some(String::from("6")),
+            mb_artist_id: Some(String::from("9fff2f8a-21e6-47de-a2b8-7f449929d43f")),
+            modified: Some(1676678400),
+            size: Some(1166),
             ..Default::default()
         };
         assert_eq!(
-            r#""test-data/songs/1.mp3","6","Passionfruit","Drake","More Life","Drake","2017","Rap","1""#,
-            info.to_string()
+            format!(
+                "{}\n{}",
+                HEADER,
+                r#""test-data/songs/1.mp3","6","Passionfruit","Drake","More Life","Drake","2017","Rap","1","","","","9fff2f8a-21e6-47de-a2b8-7f449929d43f","","1676678400","1166""#
+            ),
+            csv_rows(&[info])
         );
-        assert_eq!(info.path, "test-data/songs/1.mp3");
-        assert_eq!(info.title.unwrap(), "Passionfruit");
-        assert_eq!(info.artist.unwrap(), "Drake");
-        assert_eq!(info.album.unwrap(), "More Life");
-        assert_eq!(info.album_artist.unwrap(), "Drake");
-        assert_eq!(info.year.unwrap(), "2017");
-        assert_eq!(info.genre.unwrap(), "Rap");
-        assert_eq!(info.disc.unwrap(), "1");
-        assert_eq!(info.track.unwrap(), "6");
     }
 
     #[test]
@@ -129,8 +236,11 @@ mod tests {
             ..Default::default()
         };
         assert_eq!(
-            r#""test-data/songs/1.mp3","","","","","","","","""#,
-            info.to_string()
+            format!(
+                "{}\n{}",
+                HEADER, r#""test-data/songs/1.mp3","","","","","","","","","","","","","","","""#
+            ),
+            csv_rows(&[info])
         )
     }
 
@@ -164,6 +274,53 @@ mod tests {
         assert_eq!(info.disc.unwrap(), "1");
     }
 
+    #[test]
+    fn musicbrainz_ids_can_be_extracted() {
+        let path = std::env::temp_dir().join("playlist-maker-musicbrainz.mp3");
+        std::fs::copy("test-data/songs/id3v2.4.mp3", &path).unwrap();
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.add_frame(ExtendedText {
+            description: "MusicBrainz Album Id".to_string(),
+            value: "d3d2b8a2-0a8f-4a2b-9d7c-2f3e1f1c6c11".to_string(),
+        });
+        tag.add_frame(ExtendedText {
+            description: "MusicBrainz Release Group Id".to_string(),
+            value: "b1f9e5f4-4e1a-4d6c-8a3b-7c9d0e1f2a33".to_string(),
+        });
+        tag.add_frame(ExtendedText {
+            description: "MusicBrainz Artist Id".to_string(),
+            value: "9fff2f8a-21e6-47de-a2b8-7f449929d43f".to_string(),
+        });
+        tag.add_frame(Frame::with_content(
+            "UFID",
+            Content::Unknown(Unknown {
+                data: b"http://musicbrainz.org\0c5a1a3c8-7f0e-4a3b-9b1e-2d4f6a8c0e22".to_vec(),
+                version: Version::Id3v24,
+            }),
+        ));
+        tag.write_to_path(&path, Version::Id3v24).unwrap();
+
+        let info = TagDetails::try_from(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            info.mb_recording_id.unwrap(),
+            "c5a1a3c8-7f0e-4a3b-9b1e-2d4f6a8c0e22"
+        );
+        assert_eq!(
+            info.mb_release_id.unwrap(),
+            "d3d2b8a2-0a8f-4a2b-9d7c-2f3e1f1c6c11"
+        );
+        assert_eq!(
+            info.mb_release_group_id.unwrap(),
+            "b1f9e5f4-4e1a-4d6c-8a3b-7c9d0e1f2a33"
+        );
+        assert_eq!(
+            info.mb_artist_id.unwrap(),
+            "9fff2f8a-21e6-47de-a2b8-7f449929d43f"
+        );
+    }
+
     #[test]
     fn missing_local_song_cant_extract_info() {
         let local = TagDetails::try_from(&PathBuf::from("test-data/songs/none.mp3"));
@@ -1,4 +1,6 @@
+pub mod audio;
 pub mod checker;
 pub mod details;
 mod matcher;
-mod r#type;
+pub mod r#type;
+pub mod writer;
@@ -1,7 +1,7 @@
 use crate::tag::checker::SearchType;
 use crate::tag::details::TagDetails;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TagType {
     Path,
     Title,
@@ -12,6 +12,10 @@ pub enum TagType {
     Genre,
     Disc,
     Track,
+    MbRecordingId,
+    MbReleaseId,
+    MbReleaseGroupId,
+    MbArtistId,
 }
 
 impl TagType {
@@ -28,11 +32,15 @@ impl TagType {
             ("genre", _) => Some(TagType::Genre),
             ("discnumber" | "disc", _) => Some(TagType::Disc),
             ("track" | "tracknumber", _) => Some(TagType::Track),
+            ("mbrecordingid" | "mbtrackid", _) => Some(TagType::MbRecordingId),
+            ("mbreleaseid" | "mbalbumid", _) => Some(TagType::MbReleaseId),
+            ("mbreleasegroupid", _) => Some(TagType::MbReleaseGroupId),
+            ("mbartistid", _) => Some(TagType::MbArtistId),
             _ => None,
         }
     }
 
-    pub fn collect<'a>(&'a self, tag: &'a TagDetails) -> Option<&str> {
+    pub fn collect<'a>(&'a self, tag: &'a TagDetails) -> Option<&'a str> {
         match self {
             TagType::Path => Some(tag.path.as_str()),
             TagType::Title => tag.title.as_deref(),
@@ -43,6 +51,10 @@ impl TagType {
             TagType::Genre => tag.genre.as_deref(),
             TagType::Disc => tag.disc.as_deref(),
             TagType::Track => tag.track.as_deref(),
+            TagType::MbRecordingId => tag.mb_recording_id.as_deref(),
+            TagType::MbReleaseId => tag.mb_release_id.as_deref(),
+            TagType::MbReleaseGroupId => tag.mb_release_group_id.as_deref(),
+            TagType::MbArtistId => tag.mb_artist_id.as_deref(),
         }
     }
 }
@@ -132,4 +144,22 @@ mod tests {
 
         assert_eq!(tag, TagType::Path)
     }
+
+    #[test]
+    fn tag_type_can_be_built_as_expected_10() {
+        let tag_opt = TagType::try_from("mbartistid", &SearchType::Literal);
+        assert!(tag_opt.is_some());
+        let tag = tag_opt.unwrap();
+
+        assert_eq!(tag, TagType::MbArtistId)
+    }
+
+    #[test]
+    fn tag_type_can_be_built_as_expected_11() {
+        let tag_opt = TagType::try_from("mbalbumid", &SearchType::Contains);
+        assert!(tag_opt.is_some());
+        let tag = tag_opt.unwrap();
+
+        assert_eq!(tag, TagType::MbReleaseId)
+    }
 }
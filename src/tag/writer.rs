@@ -0,0 +1,338 @@
+use crate::query::albums::number;
+use crate::tag::checker::SearchType;
+use crate::tag::details::TagDetails;
+use crate::tag::r#type::TagType;
+use crate::utils;
+use crate::utils::paths::temporary;
+use id3::{Tag, TagLike, Timestamp, Version};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Tags that can be written by a `Set` query
+const WRITABLE: [TagType; 8] = [
+    TagType::Title,
+    TagType::Artist,
+    TagType::Album,
+    TagType::AlbumArtist,
+    TagType::Date,
+    TagType::Genre,
+    TagType::Disc,
+    TagType::Track,
+];
+
+/// New value of a tag, an empty value removing the tag
+#[derive(Debug, Eq, PartialEq)]
+pub struct Assignment {
+    pub tag: TagType,
+    pub value: String,
+}
+
+/// Change of a tag of a song, `None` being a missing tag
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Change {
+    pub path: String,
+    pub tag: TagType,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl Assignment {
+    /// Assignment of a writable tag, years being numbers and tracks and discs numbers with an
+    /// optional total, e.g. `3/12`
+    pub fn try_from(tag: &str, value: &str) -> Result<Self, String> {
+        let tag = TagType::try_from(&tag.to_lowercase(), &SearchType::Contains)
+            .filter(|tag_type| WRITABLE.contains(tag_type))
+            .ok_or_else(|| format!("tag `{}` can't be set!", tag))?;
+        let value = value.trim().to_string();
+        let valid = value.is_empty()
+            || match tag {
+                TagType::Date => value.parse::<i32>().is_ok(),
+                TagType::Disc | TagType::Track => number(Some(&value)).is_some(),
+                _ => true,
+            };
+        match valid {
+            true => Ok(Assignment { tag, value }),
+            false => Err(format!("`{}` is not a valid {:?}!", value, tag)),
+        }
+    }
+}
+
+/// Changes made by the assignments to the songs, in song order, leaving out the tags that already
+/// have their new value
+pub fn changes(songs: &[TagDetails], assignments: &[Assignment]) -> Vec<Change> {
+    let mut seen = HashSet::<&str>::new();
+    songs
+        .iter()
+        .filter(|song| seen.insert(song.path.as_str()))
+        .flat_map(|song| {
+            assignments.iter().filter_map(|assignment| {
+                let old = assignment.tag.collect(song).map(String::from);
+                let new = Some(assignment.value.to_owned()).filter(|value| !value.is_empty());
+                (old != new).then(|| Change {
+                    path: song.path.to_owned(),
+                    tag: assignment.tag,
+                    old,
+                    new,
+                })
+            })
+        })
+        .collect()
+}
+
+/// Writes the changes file by file (in parallel), returning the files that could not be written
+pub fn write(changes: &[Change]) -> Vec<String> {
+    let mut files = BTreeMap::<&str, Vec<&Change>>::new();
+    for change in changes {
+        files.entry(&change.path).or_default().push(change);
+    }
+    files
+        .into_par_iter()
+        .filter(|(path, changes)| write_file(Path::new(path), changes).is_err())
+        .map(|(path, _)| path.to_string())
+        .collect()
+}
+
+/// Sets (or removes when empty) the tag in an id3 tag
+fn apply(tag: &mut Tag, tag_type: TagType, value: &str) {
+    match (tag_type, value.is_empty()) {
+        (TagType::Title, false) => tag.set_title(value),
+        (TagType::Title, true) => tag.remove_title(),
+        (TagType::Artist, false) => tag.set_artist(value),
+        (TagType::Artist, true) => tag.remove_artist(),
+        (TagType::Album, false) => tag.set_album(value),
+        (TagType::Album, true) => tag.remove_album(),
+        (TagType::AlbumArtist, false) => tag.set_album_artist(value),
+        (TagType::AlbumArtist, true) => tag.remove_album_artist(),
+        (TagType::Genre, false) => tag.set_genre(value),
+        (TagType::Genre, true) => tag.remove_genre(),
+        // id3v2.4 tags keep the year in the recording date, see `TagDetails::try_from`
+        (TagType::Date, empty) => {
+            tag.remove_year();
+            tag.remove_date_recorded();
+            match (value.parse(), tag.version()) {
+                (Ok(year), Version::Id3v24) if !empty => tag.set_date_recorded(Timestamp {
+                    year,
+                    month: None,
+                    day: None,
+                    hour: None,
+                    minute: None,
+                    second: None,
+                }),
+                (Ok(year), _) if !empty => tag.set_year(year),
+                _ => {}
+            }
+        }
+        (TagType::Disc, _) => {
+            tag.remove_disc();
+            tag.remove_total_discs();
+            if let Some((disc, total)) = number(Some(value)) {
+                tag.set_disc(disc);
+                total
+                    .into_iter()
+                    .for_each(|total| tag.set_total_discs(total));
+            }
+        }
+        (TagType::Track, _) => {
+            tag.remove_track();
+            tag.remove_total_tracks();
+            if let Some((track, total)) = number(Some(value)) {
+                tag.set_track(track);
+                total
+                    .into_iter()
+                    .for_each(|total| tag.set_total_tracks(total));
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Writes the tag to a copy of the file (see `temporary`) that then replaces the file
+fn write_file(path: &Path, changes: &[&Change]) -> id3::Result<()> {
+    let mut tag = match Tag::read_from_path(path) {
+        Ok(tag) => tag,
+        Err(id3::Error {
+            kind: id3::ErrorKind::NoTag,
+            ..
+        }) => Tag::with_version(Version::Id3v24),
+        Err(error) => return Err(error),
+    };
+    changes.iter().for_each(|change| {
+        apply(
+            &mut tag,
+            change.tag,
+            change.new.as_deref().unwrap_or_default(),
+        )
+    });
+
+    let copy = temporary(path);
+    let written = fs::copy(path, &copy)
+        .map_err(id3::Error::from)
+        .and_then(|_| tag.write_to_path(&copy, tag.version()))
+        .and_then(|_| fs::rename(&copy, path).map_err(id3::Error::from));
+    if written.is_err() {
+        let _ = fs::remove_file(&copy);
+    }
+    written
+}
+
+/// Old and new value of every changed tag below the path of its song, followed by the number of
+/// changes and files
+pub fn diff(changes: &[Change]) -> String {
+    let mut files = Vec::<(&str, Vec<String>)>::new();
+    for change in changes {
+        if files.last().map(|(path, _)| *path) != Some(change.path.as_str()) {
+            files.push((&change.path, vec![]));
+        }
+        let lines = &mut files.last_mut().unwrap().1;
+        if let Some(old) = &change.old {
+            lines.push(format!("- {:?}: {}", change.tag, old));
+        }
+        if let Some(new) = &change.new {
+            lines.push(format!("+ {:?}: {}", change.tag, new));
+        }
+    }
+    let mut lines = files
+        .iter()
+        .map(|(path, lines)| format!("{}\n{}", path, lines.join("\n")))
+        .collect::<Vec<String>>();
+    lines.push(format!(
+        "{} tags changed in {} files",
+        changes.len(),
+        files.len()
+    ));
+    lines.join("\n\n")
+}
+
+/// Csv with one change per row
+pub fn to_csv(changes: &[Change]) -> String {
+    utils::csv::to_string(
+        &["path", "tag", "old", "new"],
+        changes.iter().map(|change| {
+            (
+                &change.path,
+                format!("{:?}", change.tag),
+                change.old.as_deref().unwrap_or_default(),
+                change.new.as_deref().unwrap_or_default(),
+            )
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tag::details::TagDetails;
+    use crate::tag::r#type::TagType;
+    use crate::tag::writer::{changes, diff, to_csv, write, Assignment};
+    use std::path::PathBuf;
+
+    #[test]
+    fn ensure_fn_try_from_works_as_expected_1() {
+        assert_eq!(
+            Assignment::try_from("AlbumArtist", "Various Artists"),
+            Ok(Assignment {
+                tag: TagType::AlbumArtist,
+                value: String::from("Various Artists"),
+            })
+        );
+        assert!(Assignment::try_from("Track", "3/12").is_ok());
+        assert!(Assignment::try_from("Year", "").is_ok());
+        assert_eq!(
+            Assignment::try_from("Year", "later"),
+            Err(String::from("`later` is not a valid Date!"))
+        );
+        assert_eq!(
+            Assignment::try_from("Path", "a.mp3"),
+            Err(String::from("tag `Path` can't be set!"))
+        );
+    }
+
+    #[test]
+    fn ensure_fn_changes_works_as_expected_1() {
+        let assignments = vec![
+            Assignment::try_from("Genre", "Jazz").unwrap(),
+            Assignment::try_from("Title", "").unwrap(),
+        ];
+
+        let changes = changes(&default_songs(), &assignments);
+
+        assert_eq!(
+            diff(&changes),
+            "a.mp3\n- Genre: Rap\n+ Genre: Jazz\n- Title: Passionfruit\n\n\
+             b.mp3\n- Title: Madiba Riddim\n\n\
+             3 tags changed in 2 files"
+        );
+        assert_eq!(
+            to_csv(&changes[..1]),
+            r#""path","tag","old","new"
+"a.mp3","Genre","Rap","Jazz""#
+        );
+    }
+
+    #[test]
+    fn ensure_fn_write_works_as_expected_1() {
+        let copies = ["id3v2.3", "id3v2.4"].map(|name| {
+            let copy = std::env::temp_dir().join(format!("playlist-maker-write-{}.mp3", name));
+            std::fs::copy(format!("test-data/songs/{}.mp3", name), &copy).unwrap();
+            copy
+        });
+        let songs = copies
+            .iter()
+            .map(|copy| TagDetails::try_from(copy).unwrap())
+            .collect::<Vec<TagDetails>>();
+        let assignments = vec![
+            Assignment::try_from("Genre", "Jazz").unwrap(),
+            Assignment::try_from("Year", "2020").unwrap(),
+            Assignment::try_from("Track", "3/12").unwrap(),
+        ];
+
+        let failed = write(&changes(&songs, &assignments));
+        let written = copies
+            .iter()
+            .map(|copy| TagDetails::try_from(copy).unwrap())
+            .collect::<Vec<TagDetails>>();
+        let missing = write(&changes(
+            &[TagDetails {
+                path: String::from("/missing/song.mp3"),
+                ..Default::default()
+            }],
+            &assignments,
+        ));
+        copies
+            .iter()
+            .for_each(|copy| std::fs::remove_file(copy).unwrap());
+
+        assert!(failed.is_empty());
+        for song in written {
+            assert_eq!(song.genre.as_deref(), Some("Jazz"));
+            assert_eq!(song.year.as_deref(), Some("2020"));
+            assert_eq!(song.track.as_deref(), Some("3"));
+            assert_eq!(song.title.as_deref(), Some("Passionfruit"));
+        }
+        assert_eq!(missing, vec!["/missing/song.mp3"]);
+        assert!(!PathBuf::from("/missing/.song.mp3.pl-mker.tmp").exists());
+    }
+
+    fn default_songs() -> Vec<TagDetails> {
+        vec![
+            TagDetails {
+                path: String::from("a.mp3"),
+                title: Some(String::from("Passionfruit")),
+                genre: Some(String::from("Rap")),
+                ..Default::default()
+            },
+            TagDetails {
+                path: String::from("b.mp3"),
+                title: Some(String::from("Madiba Riddim")),
+                genre: Some(String::from("Jazz")),
+                ..Default::default()
+            },
+            TagDetails {
+                path: String::from("c.mp3"),
+                genre: Some(String::from("Jazz")),
+                ..Default::default()
+            },
+        ]
+    }
+}
@@ -0,0 +1,44 @@
+use csv::{QuoteStyle, Terminator, WriterBuilder};
+use serde::Serialize;
+
+/// Csv with every value quoted, the header and then one row per item, each row being a struct or a
+/// sequence of values
+pub fn to_string<R: Serialize>(header: &[&str], rows: impl IntoIterator<Item = R>) -> String {
+    let mut csv = WriterBuilder::new()
+        .quote_style(QuoteStyle::Always)
+        .terminator(Terminator::Any(b'\n'))
+        .has_headers(false)
+        .from_writer(vec![]);
+    // writing into memory can't fail and rows only have strings and numbers
+    csv.write_record(header).expect("Invalid Row");
+    rows.into_iter()
+        .for_each(|row| csv.serialize(row).expect("Invalid Row"));
+    String::from_utf8(csv.into_inner().expect("Invalid Row"))
+        .expect("Invalid Row")
+        .trim_end_matches('\n')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::csv::to_string;
+
+    #[test]
+    fn ensure_fn_to_string_works_as_expected_1() {
+        let rows = vec![("a", 1), ("b \"c\"", 2)];
+
+        let output = to_string(&["name", "count"], rows);
+
+        assert_eq!(
+            output,
+            "\"name\",\"count\"\n\"a\",\"1\"\n\"b \"\"c\"\"\",\"2\""
+        );
+    }
+
+    #[test]
+    fn ensure_fn_to_string_works_as_expected_2() {
+        let output = to_string::<[&str; 2]>(&["from", "to"], []);
+
+        assert_eq!(output, "\"from\",\"to\"");
+    }
+}
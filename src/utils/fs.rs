@@ -1,93 +1,463 @@
-use crate::playlist::Playlist;
-use crate::tag::details::TagDetails;
+use crate::index;
+use crate::index::inverted::Postings;
+use crate::index::IndexFormat;
+use crate::playlist::{m3u, pls, xspf};
+use crate::playlist::{Playlist, QUERY_PREFIX};
+use crate::query::library::QueryLibrary;
+use crate::query::processor;
+use crate::tag::details::{file_stamp, TagDetails};
 use crate::utils::matching::ExtensionExtractor;
+use crate::utils::paths::{absolute, common_dir, normalize, temporary};
 use rayon::prelude::*;
-use std::collections::HashSet;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::exit;
 use walkdir::WalkDir;
 
-pub fn get_songs(input: Vec<PathBuf>) -> Vec<TagDetails> {
+pub fn get_songs(input: Vec<PathBuf>, music_root: Option<&Path>) -> Vec<TagDetails> {
+    load_songs(input, music_root, &HashMap::new())
+}
+
+/// Same as `get_songs` but also returns the postings of the input when it is a single inverted
+/// index, its songs then being kept in the order of the index since postings refer to them by
+/// position. With any other input the postings are not used, songs read from elsewhere have no
+/// position in them.
+pub fn get_songs_with_postings(
+    input: Vec<PathBuf>,
+    music_root: Option<&Path>,
+) -> (Vec<TagDetails>, Option<Postings>) {
+    match input.as_slice() {
+        [file] if file.is_file() && index::format(file) == IndexFormat::Inverted => {
+            index::read_with_postings(file, music_root)
+        }
+        _ => (get_songs(input, music_root), None),
+    }
+}
+
+/// Directory the songs of `input` are stored under, the deepest one containing the input
+/// directories and the library roots of the input indexes (`music_root` when given). `None` when
+/// an index has no recorded root
+pub fn library_root(input: &[PathBuf], music_root: Option<&Path>) -> Option<PathBuf> {
+    input
+        .iter()
+        .filter(|path| path.is_dir() || path.is_file())
+        .map(|path| match path.is_dir() {
+            true => Some(path.to_path_buf()),
+            false => music_root
+                .map(|root| root.to_path_buf())
+                .or_else(|| index::read_root(path).map(PathBuf::from)),
+        })
+        .collect::<Option<Vec<PathBuf>>>()
+        .and_then(|roots| common_dir(roots.into_iter()))
+}
+
+/// Same as `get_songs` but songs found in `index` whose file did not change since they were indexed
+/// are reused instead of having their tags read again. Deleted files are dropped since only the
+/// songs found in `input` are returned.
+pub fn update_songs(
+    input: Vec<PathBuf>,
+    music_root: Option<&Path>,
+    index: &Path,
+) -> Vec<TagDetails> {
+    let indexed = if index.is_file() {
+        export(index.to_path_buf(), None)
+            .into_iter()
+            .map(|song| (absolute(Path::new(&song.path)), song))
+            .collect::<HashMap<PathBuf, TagDetails>>()
+    } else {
+        HashMap::new()
+    };
+    load_songs(input, music_root, &indexed)
+}
+
+fn load_songs(
+    input: Vec<PathBuf>,
+    music_root: Option<&Path>,
+    indexed: &HashMap<PathBuf, TagDetails>,
+) -> Vec<TagDetails> {
     input
         .into_iter()
         .filter(|dir| dir.is_dir() || dir.is_file())
-        .flat_map(|dir| if dir.is_dir() { walk(dir) } else { export(dir) })
+        .flat_map(|dir| {
+            if dir.is_dir() {
+                walk(dir, indexed)
+            } else {
+                export(dir, music_root)
+            }
+        })
         .collect::<HashSet<TagDetails>>()
         .into_iter()
         .collect::<Vec<TagDetails>>()
 }
 
-fn export(file: PathBuf) -> Vec<TagDetails> {
-    csv::ReaderBuilder::new()
-        .delimiter(b',')
-        .double_quote(true)
-        .has_headers(true)
-        .from_path(file.into_os_string())
-        .expect("Invalid File")
-        .deserialize::<TagDetails>()
-        .filter_map(|record| record.ok())
-        .collect::<Vec<TagDetails>>()
+fn export(file: PathBuf, music_root: Option<&Path>) -> Vec<TagDetails> {
+    index::read(&file, music_root)
 }
 
-fn walk(dir: PathBuf) -> Vec<TagDetails> {
+fn walk(dir: PathBuf, indexed: &HashMap<PathBuf, TagDetails>) -> Vec<TagDetails> {
     WalkDir::new(dir)
         .into_iter()
         .filter_entry(|entry| entry.path().is_dir_or_has_extension("mp3"))
         .par_bridge()
         .filter_map(|entry| entry.map(|e| e.into_path()).ok())
         .filter(|entry| entry.is_file())
-        .filter_map(|path| TagDetails::try_from(&path).ok())
+        .filter_map(|path| match indexed.get(&absolute(&path)) {
+            Some(song) if is_up_to_date(song, &path) => Some(TagDetails {
+                path: path.to_string_lossy().to_string(),
+                ..song.to_owned()
+            }),
+            _ => TagDetails::try_from(&path).ok(),
+        })
         .collect::<Vec<TagDetails>>()
 }
 
-pub fn get_playlists(playlists: Vec<PathBuf>) -> Vec<Playlist> {
+fn is_up_to_date(song: &TagDetails, path: &Path) -> bool {
+    match (song.modified, song.size, file_stamp(path)) {
+        (Some(modified), Some(size), Some(stamp)) => (modified, size) == stamp,
+        _ => false,
+    }
+}
+
+/// Playlists given directly are named by their file name (without extension), playlists found in
+/// `playlist_dirs` by their path relative to the directory (e.g. `2023/summer`). Playlists included
+/// by others are read in their place and saved queries they reference are taken from `library`
+pub fn get_playlists(
+    playlists: Vec<PathBuf>,
+    playlist_dirs: Vec<PathBuf>,
+    ignore_case: bool,
+    library: &QueryLibrary,
+) -> Vec<Playlist> {
+    let named = playlists
+        .iter()
+        .flat_map(|playlist| expand(playlist))
+        .map(|path| {
+            let name = path.file_stem().unwrap().to_string_lossy().to_string();
+            (name, path)
+        })
+        .chain(playlist_dirs.iter().flat_map(|dir| find_playlists(dir)))
+        .collect::<Vec<(String, PathBuf)>>();
+
+    let mut paths_by_name = HashMap::<&str, &Path>::new();
+    for (name, path) in named.iter() {
+        if let Some(other) = paths_by_name.insert(name, path) {
+            println!(
+                "playlist name `{}` is ambiguous (`{}` and `{}`)!",
+                name,
+                other.display(),
+                path.display()
+            );
+            exit(2);
+        }
+    }
+
     let mut playlist_vec = Vec::new();
-    for playlist in playlists {
-        let path = Path::new(&playlist);
-        if path.has_extension("m3u") {
-            playlist_vec.push(Playlist {
-                name: path.file_stem().unwrap().to_string_lossy().to_string(),
-                songs: BufReader::new(File::open(path).unwrap())
-                    .lines()
-                    .filter_map(|line| line.ok())
-                    .collect(),
-            });
+    for (name, path) in named {
+        let (songs, queries) = read_entries(&path, &mut Vec::new());
+        let mut playlist = Playlist::new(name, songs, ignore_case);
+        playlist.queries = queries
+            .iter()
+            .map(|query| {
+                library.resolve_saved(query).unwrap_or_else(|error| {
+                    println!("{} (referenced by playlist `{}`)", error, path.display());
+                    exit(2);
+                })
+            })
+            .collect();
+        playlist_vec.push(playlist);
+    }
+    check_references(&playlist_vec);
+    playlist_vec
+}
+
+/// Songs and saved queries of a playlist, the ones of the playlists it includes in their place.
+/// `stack` has the playlists being read, to find playlists that include themselves
+fn read_entries(path: &Path, stack: &mut Vec<PathBuf>) -> (Vec<String>, Vec<String>) {
+    let entries = read_playlist(path).unwrap_or_else(|| {
+        println!(
+            "playlist `{}` does not exist or is invalid (not m3u, m3u8, pls or xspf)!",
+            path.display()
+        );
+        exit(2);
+    });
+
+    stack.push(normalize(path));
+    let (mut songs, mut queries) = (Vec::new(), Vec::new());
+    for entry in entries {
+        if let Some(query) = entry.strip_prefix(QUERY_PREFIX) {
+            queries.push(query.trim().to_string());
+        } else if is_playlist(Path::new(&entry)) {
+            let nested = normalize(Path::new(&entry));
+            if stack.contains(&nested) {
+                println!(
+                    "playlist `{}` includes itself ({} -> {})!",
+                    nested.display(),
+                    stack
+                        .iter()
+                        .map(|path| path.display().to_string())
+                        .collect::<Vec<String>>()
+                        .join(" -> "),
+                    nested.display()
+                );
+                exit(2);
+            }
+            let (nested_songs, nested_queries) = read_entries(&nested, stack);
+            songs.extend(nested_songs);
+            queries.extend(nested_queries);
         } else {
+            songs.push(entry);
+        }
+    }
+    stack.pop();
+    (songs, queries)
+}
+
+/// Playlists can also reference each other through the saved queries they include, which would
+/// never end if one of them ends up referencing itself. The saved queries must also be valid
+/// expressions, since they are only run once the songs are loaded
+fn check_references(playlists: &[Playlist]) {
+    for playlist in playlists {
+        if let Some(query) = playlist
+            .queries
+            .iter()
+            .find(|query| !processor::is_query_expr(query))
+        {
             println!(
-                "playlist `{}` does not exist or is invalid (not m3u)!",
-                playlist.display()
+                "playlist `{}` has an invalid saved query `{}`!",
+                playlist.name, query
             );
             exit(2);
         }
     }
-    playlist_vec
+
+    let references = playlists
+        .iter()
+        .map(|playlist| {
+            let names = playlist
+                .queries
+                .iter()
+                .flat_map(|query| processor::playlist_names(query))
+                .collect::<Vec<String>>();
+            (playlist.name.as_str(), names)
+        })
+        .collect::<HashMap<&str, Vec<String>>>();
+
+    fn visit<'a>(
+        name: &'a str,
+        references: &'a HashMap<&str, Vec<String>>,
+        stack: &mut Vec<&'a str>,
+    ) -> Option<String> {
+        if stack.contains(&name) {
+            return Some(format!("{} -> {}", stack.join(" -> "), name));
+        }
+        stack.push(name);
+        let cycle = references
+            .get(name)
+            .into_iter()
+            .flatten()
+            .find_map(|other| visit(other, references, stack));
+        stack.pop();
+        cycle
+    }
+
+    for playlist in playlists {
+        if let Some(cycle) = visit(&playlist.name, &references, &mut Vec::new()) {
+            println!(
+                "playlist `{}` references itself through saved queries ({})!",
+                playlist.name, cycle
+            );
+            exit(2);
+        }
+    }
+}
+
+/// Rewrites the playlists (given or found in the playlist directories) referencing moved songs,
+/// `moved` giving the new path of a song by its path. Returns the playlists that could not be
+/// rewritten
+pub fn relocate_playlists(
+    playlists: &[PathBuf],
+    playlist_dirs: &[PathBuf],
+    moved: &impl Fn(&str) -> Option<PathBuf>,
+) -> Vec<PathBuf> {
+    playlists
+        .iter()
+        .flat_map(|playlist| expand(playlist))
+        .chain(
+            playlist_dirs
+                .iter()
+                .flat_map(|dir| find_playlists(dir).into_iter().map(|(_, path)| path)),
+        )
+        .collect::<BTreeSet<PathBuf>>()
+        .into_iter()
+        .filter(|path| relocate_playlist(path, moved).is_none())
+        .collect()
+}
+
+/// Playlists are only written when an entry changed, to a temporary file that then replaces them
+fn relocate_playlist(path: &Path, moved: &impl Fn(&str) -> Option<PathBuf>) -> Option<()> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let (content, relocated) = if path.has_extension("m3u8") || path.has_extension("m3u") {
+        let content = m3u::decode(fs::read(path).ok()?, path.has_extension("m3u8"));
+        let relocated = m3u::relocate(&content, dir, moved);
+        (content, relocated)
+    } else if path.has_extension("pls") {
+        let content = pls::decode(fs::read(path).ok()?);
+        let relocated = pls::relocate(&content, dir, moved);
+        (content, relocated)
+    } else {
+        let content = fs::read_to_string(path).ok()?;
+        let relocated = xspf::relocate(&content, dir, moved);
+        (content, relocated)
+    };
+    if relocated != content {
+        let copy = temporary(path);
+        if fs::write(&copy, relocated)
+            .and_then(|_| fs::rename(&copy, path))
+            .is_err()
+        {
+            let _ = fs::remove_file(&copy);
+            return None;
+        }
+    }
+    Some(())
+}
+
+/// Rewrites the indexes of `input` listing moved songs, `moved` giving the new path of a song by its
+/// path, so they don't keep the old paths. Returns the indexes that could not be rewritten
+pub fn relocate_indexes(
+    input: &[PathBuf],
+    music_root: Option<&Path>,
+    moved: &impl Fn(&str) -> Option<PathBuf>,
+) -> Vec<PathBuf> {
+    input
+        .iter()
+        .filter(|path| path.is_file())
+        .filter(|path| relocate_index(path, music_root, moved).is_none())
+        .cloned()
+        .collect()
+}
+
+/// Indexes are only written when a song moved, keeping their format and library root, to a
+/// temporary file that then replaces them
+fn relocate_index(
+    path: &Path,
+    music_root: Option<&Path>,
+    moved: &impl Fn(&str) -> Option<PathBuf>,
+) -> Option<()> {
+    let mut songs = index::read(path, music_root);
+    let mut relocated = false;
+    for song in songs.iter_mut() {
+        if let Some(to) = moved(&song.path) {
+            song.path = to.to_string_lossy().to_string();
+            relocated = true;
+        }
+    }
+    if relocated {
+        let root = music_root
+            .map(|root| root.to_path_buf())
+            .or_else(|| index::read_root(path).map(PathBuf::from));
+        let copy = temporary(path);
+        if index::write_as(&copy, index::format(path), root.as_deref(), &songs)
+            .and_then(|_| fs::rename(&copy, path).ok())
+            .is_none()
+        {
+            let _ = fs::remove_file(&copy);
+            return None;
+        }
+    }
+    Some(())
+}
+
+fn is_playlist(path: &Path) -> bool {
+    PLAYLIST_EXTENSIONS.iter().any(|e| path.has_extension(e))
+}
+
+/// Playlists matching a glob pattern, or the playlist itself if it exists or nothing matches
+fn expand(playlist: &Path) -> Vec<PathBuf> {
+    if playlist.exists() {
+        return vec![playlist.to_path_buf()];
+    }
+    let mut paths = glob::glob(&playlist.to_string_lossy())
+        .map(|paths| paths.filter_map(Result::ok).collect::<Vec<PathBuf>>())
+        .unwrap_or_default();
+    if paths.is_empty() {
+        paths.push(playlist.to_path_buf());
+    }
+    paths.sort();
+    paths
+}
+
+fn find_playlists(dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut playlists = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.map(|e| e.into_path()).ok())
+        .filter(|path| is_playlist(path))
+        .filter_map(|path| {
+            let name = path
+                .strip_prefix(dir)
+                .ok()?
+                .with_extension("")
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            Some((name, path))
+        })
+        .collect::<Vec<(String, PathBuf)>>();
+    playlists.sort();
+    playlists
+}
+
+const PLAYLIST_EXTENSIONS: [&str; 4] = ["m3u", "m3u8", "pls", "xspf"];
+
+fn read_playlist(path: &Path) -> Option<Vec<String>> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    if path.has_extension("m3u8") || path.has_extension("m3u") {
+        let bytes = fs::read(path).ok()?;
+        Some(m3u::parse(
+            &m3u::decode(bytes, path.has_extension("m3u8")),
+            dir,
+        ))
+    } else if path.has_extension("pls") {
+        Some(pls::parse(&pls::decode(fs::read(path).ok()?), dir))
+    } else if path.has_extension("xspf") {
+        xspf::parse(&fs::read_to_string(path).ok()?, dir)
+    } else {
+        None
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::utils::fs::{export, get_playlists, get_songs, walk};
-    use std::path::PathBuf;
+    use crate::query::library::QueryLibrary;
+    use crate::query::processor::QueryType;
+    use crate::tag::details::TagDetails;
+    use crate::utils::fs::{
+        export, get_playlists, get_songs, get_songs_with_postings, library_root, update_songs, walk,
+    };
+    use crate::utils::paths::absolute;
+    use crate::utils::printer::{Output, Printer};
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
 
     #[test]
     fn ensure_fn_export_works_as_expected() {
         let input = PathBuf::from("test-data/index.csv");
-        let songs = export(input);
+        let songs = export(input, None);
         assert_eq!(songs.len(), 17)
     }
 
     #[test]
     fn ensure_fn_walk_works_as_expected() {
         let input = PathBuf::from("test-data");
-        let songs = walk(input);
+        let songs = walk(input, &HashMap::new());
         assert_eq!(songs.len(), 2)
     }
 
     #[test]
     fn ensure_fn_get_songs_works_as_expected_1() {
         let input = vec![PathBuf::from("test-data/index.csv")];
-        let songs = get_songs(input);
+        let songs = get_songs(input, None);
         assert_eq!(songs.len(), 13)
     }
 
@@ -97,28 +467,225 @@ mod tests {
             PathBuf::from("test-data/index.csv"),
             PathBuf::from("test-data"),
         ];
-        let songs = get_songs(input);
+        let songs = get_songs(input, None);
         assert_eq!(songs.len(), 15)
     }
 
     #[test]
-    fn ensure_fn_get_playlists_works_as_expected() {
+    fn ensure_fn_get_songs_with_postings_works_as_expected_1() {
+        let input = vec![PathBuf::from("test-data/index.csv")];
+        let (songs, postings) = get_songs_with_postings(input, None);
+        assert_eq!(songs.len(), 13);
+        assert!(postings.is_none());
+    }
+
+    #[test]
+    fn ensure_fn_get_songs_with_postings_works_as_expected_2() {
+        let index = std::env::temp_dir().join("playlist-maker-songs-with-postings-2.inv");
+        Printer {
+            output: Output::File(index.clone()),
+            print_type: QueryType::Index,
+            ..Default::default()
+        }
+        .print(&export(PathBuf::from("test-data/index.csv"), None), &[]);
+
+        let (songs, postings) = get_songs_with_postings(vec![index.clone()], None);
+        std::fs::remove_file(&index).unwrap();
+
+        // every row of the index is kept, duplicated songs included, in the order of the index
+        assert_eq!(songs.len(), 17);
+        assert_eq!(
+            songs
+                .iter()
+                .map(|song| song.path.as_str())
+                .collect::<Vec<&str>>(),
+            export(PathBuf::from("test-data/index.csv"), None)
+                .iter()
+                .map(|song| song.path.as_str())
+                .collect::<Vec<&str>>()
+        );
+        assert!(postings.is_some());
+    }
+
+    #[test]
+    fn ensure_fn_library_root_works_as_expected_1() {
+        let index = std::env::temp_dir().join("playlist-maker-library-root-1.csv");
+        std::fs::write(
+            &index,
+            "#playlist-maker-index v5\n#library-root /music/Joji\n",
+        )
+        .unwrap();
+
+        let dirs = library_root(
+            &[
+                PathBuf::from("test-data/songs"),
+                PathBuf::from("test-data/playlists"),
+            ],
+            None,
+        );
+        let indexed = library_root(std::slice::from_ref(&index), None);
+        let moved = library_root(std::slice::from_ref(&index), Some(Path::new("/mnt/music")));
+        std::fs::remove_file(&index).unwrap();
+
+        assert_eq!(dirs, Some(absolute(Path::new("test-data"))));
+        assert_eq!(indexed, Some(PathBuf::from("/music/Joji")));
+        assert_eq!(moved, Some(PathBuf::from("/mnt/music")));
+        assert_eq!(
+            library_root(&[PathBuf::from("test-data/index.csv")], None),
+            None
+        );
+    }
+
+    #[test]
+    fn ensure_fn_update_songs_works_as_expected_1() {
+        let index = std::env::temp_dir().join("playlist-maker-update-songs-1.csv");
+        let _ = std::fs::remove_file(&index);
+
+        let songs = update_songs(vec![PathBuf::from("test-data/songs")], None, &index);
+
+        assert_eq!(songs.len(), 2);
+        assert!(songs.iter().all(|song| song.title.is_some()));
+    }
+
+    #[test]
+    fn ensure_fn_update_songs_works_as_expected_2() {
+        let index = std::env::temp_dir().join("playlist-maker-update-songs-2.csv");
+        let mut songs = walk(PathBuf::from("test-data/songs"), &HashMap::new());
+        songs.push(TagDetails {
+            path: "test-data/songs/deleted.mp3".to_string(),
+            ..Default::default()
+        });
+        for song in songs.iter_mut() {
+            song.title = Some("Indexed".to_string());
+        }
+        Printer {
+            output: Output::File(index.clone()),
+            print_type: QueryType::Index,
+            ..Default::default()
+        }
+        .print(&songs, &[]);
+
+        let updated = update_songs(vec![PathBuf::from("test-data/songs")], None, &index);
+        std::fs::remove_file(&index).unwrap();
+
+        assert_eq!(updated.len(), 2);
+        assert!(updated
+            .iter()
+            .all(|song| song.title.as_deref() == Some("Indexed")));
+    }
+
+    #[test]
+    fn ensure_fn_get_playlists_works_as_expected_1() {
         let input = vec![PathBuf::from("test-data/playlist.m3u")];
-        let playlists = get_playlists(input);
+        let playlists = get_playlists(input, vec![], false, &QueryLibrary::default());
         assert_eq!(playlists.len(), 1);
-        assert_eq!(playlists.get(0).unwrap().name, "playlist");
-        assert_eq!(playlists.get(0).unwrap().songs.len(), 3);
+        assert_eq!(playlists.first().unwrap().name, "playlist");
+        assert_eq!(playlists.first().unwrap().songs.len(), 3);
         assert_eq!(
-            playlists.get(0).unwrap().songs.get(0).unwrap(),
+            playlists.first().unwrap().songs.first().unwrap(),
             "test-data/songs/1.mp3"
         );
         assert_eq!(
-            playlists.get(0).unwrap().songs.get(1).unwrap(),
+            playlists.first().unwrap().songs.get(1).unwrap(),
             "test-data/songs/2.mp3"
         );
         assert_eq!(
-            playlists.get(0).unwrap().songs.get(2).unwrap(),
+            playlists.first().unwrap().songs.get(2).unwrap(),
             "test-data/songs/3.mp3"
         );
     }
+
+    #[test]
+    fn ensure_fn_get_playlists_works_as_expected_2() {
+        let input = vec![PathBuf::from("test-data/extended.m3u8")];
+        let playlists = get_playlists(input, vec![], false, &QueryLibrary::default());
+        assert_eq!(playlists.len(), 1);
+        assert_eq!(playlists.first().unwrap().name, "extended");
+        assert_eq!(
+            playlists.first().unwrap().songs,
+            vec!["test-data/songs/id3v2.3.mp3", "test-data/songs/id3v2.4.mp3"]
+        );
+    }
+
+    #[test]
+    fn ensure_fn_get_playlists_works_as_expected_3() {
+        for input in ["test-data/playlist.pls", "test-data/playlist.xspf"] {
+            let playlists = get_playlists(
+                vec![PathBuf::from(input)],
+                vec![],
+                false,
+                &QueryLibrary::default(),
+            );
+            assert_eq!(playlists.len(), 1);
+            assert_eq!(playlists.first().unwrap().name, "playlist");
+            assert_eq!(
+                playlists.first().unwrap().songs,
+                vec!["test-data/songs/1.mp3", "test-data/songs/2.mp3"]
+            );
+        }
+    }
+
+    #[test]
+    fn ensure_fn_get_playlists_works_as_expected_4() {
+        let input = vec![PathBuf::from("test-data/*.m3u*")];
+        let playlists = get_playlists(input, vec![], false, &QueryLibrary::default());
+        let names = playlists
+            .iter()
+            .map(|playlist| playlist.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(names, vec!["extended", "playlist"]);
+    }
+
+    #[test]
+    fn ensure_fn_get_playlists_works_as_expected_5() {
+        let dirs = vec![PathBuf::from("test-data/playlists")];
+        let playlists = get_playlists(vec![], dirs, false, &QueryLibrary::default());
+        let names = playlists
+            .iter()
+            .map(|playlist| playlist.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(names, vec!["2023/summer", "2024/summer", "favourites"]);
+        assert_eq!(
+            playlists.first().unwrap().songs,
+            vec!["test-data/playlists/2023/../../songs/id3v2.3.mp3"]
+        );
+    }
+
+    #[test]
+    fn ensure_fn_get_playlists_works_as_expected_6() {
+        let input = vec![PathBuf::from("test-data/nested/all.m3u")];
+        let library = QueryLibrary::read(Path::new("test-data/nested/queries.lib"));
+        let playlists = get_playlists(input, vec![], false, &library);
+        assert_eq!(playlists.len(), 1);
+        assert_eq!(
+            playlists.first().unwrap().songs,
+            vec![
+                "test-data/nested/../songs/id3v2.4.mp3".to_string(),
+                absolute(Path::new(
+                    "test-data/playlists/2023/../../songs/id3v2.3.mp3"
+                ))
+                .to_string_lossy()
+                .to_string()
+            ]
+        );
+        assert_eq!(
+            playlists.first().unwrap().queries,
+            vec![r#"(Artist("Drake"))"#]
+        );
+    }
+
+    #[test]
+    fn ensure_fn_get_playlists_works_as_expected_7() {
+        let input = vec![PathBuf::from("test-data/nested/relative.m3u")];
+        let playlists = get_playlists(input, vec![], false, &QueryLibrary::default());
+        assert_eq!(playlists.len(), 1);
+        assert_eq!(
+            playlists.first().unwrap().songs,
+            vec![
+                "test-data/nested/../songs/1.mp3",
+                "test-data/nested/../songs/2.mp3",
+                "test-data/nested/../songs/3.mp3"
+            ]
+        );
+    }
 }
@@ -13,13 +13,14 @@ impl<T: Clone> AlmostEqualDivision<T> for Vec<T> {
         }
 
         let (all, remainder) = self.split_at(at);
+        let mut remainder = remainder.iter();
 
         all.chunks_exact(self.len() / div)
-            .map(|arr| arr.to_vec())
-            .into_iter()
-            .zip(remainder.iter())
-            .map(|(mut arr, elem)| {
-                arr.push(elem.clone());
+            .map(|arr| {
+                let mut arr = arr.to_vec();
+                if let Some(elem) = remainder.next() {
+                    arr.push(elem.clone());
+                }
                 arr
             })
             .collect()
@@ -71,4 +72,18 @@ mod tests {
             assert!(x.len() <= 1);
         }
     }
+
+    #[test]
+    fn ensure_fn_almost_equal_div_works_as_expected_6() {
+        let out = (0..10).collect::<Vec<i32>>().divide_collection_by(1);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].len(), 10);
+    }
+
+    #[test]
+    fn ensure_fn_almost_equal_div_works_as_expected_7() {
+        let out = (0..10).collect::<Vec<i32>>().divide_collection_by(4);
+        assert_eq!(out.len(), 4);
+        assert_eq!(out.iter().map(|x| x.len()).sum::<usize>(), 10);
+    }
 }
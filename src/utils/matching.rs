@@ -10,7 +10,7 @@ impl ExtensionExtractor for Path {
         self.is_file()
             && self
                 .extension()
-                .map_or(false, |e| e.eq_ignore_ascii_case(extension))
+                .is_some_and(|e| e.eq_ignore_ascii_case(extension))
     }
 
     fn is_dir_or_has_extension(&self, extension: &str) -> bool {
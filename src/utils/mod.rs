@@ -1,4 +1,8 @@
+pub mod csv;
 pub mod fs;
 pub mod iter;
 mod matching;
+pub mod moves;
+pub mod paths;
 pub mod printer;
+pub mod template;
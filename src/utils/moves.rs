@@ -0,0 +1,366 @@
+use crate::tag::details::TagDetails;
+use crate::utils;
+use crate::utils::paths::{absolute, canonical, normalize};
+use crate::utils::template::Template;
+use csv::{QuoteStyle, Terminator};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Characters left out of the values written in a path, separators and the ones not allowed in
+/// file names by common filesystems
+const RESERVED: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Move of a song to the path given by the layout of a `Move` query
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Move {
+    pub from: String,
+    pub to: String,
+    /// Whether another song goes to the same path or a file is already there, such songs are
+    /// never moved
+    pub collision: bool,
+}
+
+/// Moves of the songs, relative layouts being resolved against the library root (see
+/// `is_relative`). Songs already where the layout puts them are left out
+pub fn plan(songs: &[TagDetails], layout: &Template, library_root: Option<&Path>) -> Vec<Move> {
+    let root = library_root.unwrap_or(Path::new(""));
+    let mut seen = HashSet::<&str>::new();
+    let mut moves = songs
+        .iter()
+        .filter(|song| seen.insert(song.path.as_str()))
+        .map(|song| Move {
+            from: song.path.to_owned(),
+            to: normalize(&root.join(clean(&layout.render_with(song, sanitize))))
+                .to_string_lossy()
+                .to_string(),
+            collision: false,
+        })
+        .filter(|planned| normalize(Path::new(&planned.from)).to_string_lossy() != planned.to)
+        .collect::<Vec<Move>>();
+
+    // paths are compared ignoring their case, to be safe on case-insensitive filesystems
+    let mut destinations = HashMap::<String, usize>::new();
+    for planned in moves.iter() {
+        *destinations.entry(planned.to.to_lowercase()).or_default() += 1;
+    }
+    for planned in moves.iter_mut() {
+        let to = Path::new(&planned.to);
+        planned.collision = destinations[&planned.to.to_lowercase()] > 1
+            || (to.exists() && canonical(to) != canonical(Path::new(&planned.from)));
+    }
+    moves
+}
+
+/// Whether the paths given by the layout are relative, which only depends on its text since the
+/// tag values written in it have no separators
+pub fn is_relative(layout: &Template) -> bool {
+    Path::new(&layout.render_with(&TagDetails::default(), sanitize)).is_relative()
+}
+
+/// Moves the songs without a collision, creating the directories needed. Files are renamed, or
+/// copied and then removed when they go to another filesystem, never replacing a file already
+/// there. Returns the moves made and the songs that could not be moved
+pub fn apply(moves: &[Move]) -> (Vec<&Move>, Vec<String>) {
+    let (mut moved, mut failed) = (vec![], vec![]);
+    for planned in moves.iter().filter(|planned| !planned.collision) {
+        match move_file(Path::new(&planned.from), Path::new(&planned.to)) {
+            Some(_) => moved.push(planned),
+            None => failed.push(planned.from.to_owned()),
+        }
+    }
+    (moved, failed)
+}
+
+fn move_file(from: &Path, to: &Path) -> Option<()> {
+    fs::create_dir_all(to.parent()?).ok()?;
+    // the destination is created first, so a file put there since the moves were planned is never
+    // replaced by the rename or the copy
+    match OpenOptions::new().write(true).create_new(true).open(to) {
+        Ok(_) => {}
+        // the same file, e.g. only the case of its name changes
+        Err(_) if canonical(to) == canonical(from) => return fs::rename(from, to).ok(),
+        Err(_) => return None,
+    }
+    if fs::rename(from, to).is_ok() {
+        return Some(());
+    }
+    let copied = File::open(from)
+        .and_then(|mut song| {
+            let mut file = OpenOptions::new().write(true).truncate(true).open(to)?;
+            io::copy(&mut song, &mut file)?;
+            fs::set_permissions(to, song.metadata()?.permissions())
+        })
+        .and_then(|_| fs::remove_file(from));
+    match copied {
+        Ok(_) => Some(()),
+        Err(_) => {
+            let _ = fs::remove_file(to);
+            None
+        }
+    }
+}
+
+/// Appends the moves made to a csv log with a `from` and `to` column (written when the log is
+/// created), moving every `to` back to its `from` from the last row up undoes them
+pub fn log(file: &Path, moved: &[&Move]) -> Option<()> {
+    let new = fs::metadata(file).map_or(true, |metadata| metadata.len() == 0);
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(file)
+        .ok()?;
+    let mut csv = csv::WriterBuilder::new()
+        .quote_style(QuoteStyle::Always)
+        .terminator(Terminator::Any(b'\n'))
+        .from_writer(log);
+    if new {
+        csv.write_record(["from", "to"]).ok()?;
+    }
+    for planned in moved {
+        let from = absolute(Path::new(&planned.from));
+        csv.write_record([&*from.to_string_lossy(), &planned.to])
+            .ok()?;
+    }
+    csv.flush().ok()
+}
+
+/// Each move as `<from> -> <to>`, collisions marked, followed by the number of moves and
+/// collisions
+pub fn report(moves: &[Move]) -> String {
+    let mut lines = moves
+        .iter()
+        .map(|planned| {
+            format!(
+                "{}{} -> {}",
+                if planned.collision { "collision: " } else { "" },
+                planned.from,
+                planned.to
+            )
+        })
+        .collect::<Vec<String>>();
+    let collisions = moves.iter().filter(|planned| planned.collision).count();
+    lines.push(String::new());
+    lines.push(format!(
+        "{} moves, {} collisions (not moved)",
+        moves.len() - collisions,
+        collisions
+    ));
+    lines.join("\n")
+}
+
+/// Csv with one move per row
+pub fn to_csv(moves: &[Move]) -> String {
+    utils::csv::to_string(&["from", "to", "collision"], moves)
+}
+
+/// Value of a tag as it can be written in a file name, values made of dots (which would be the
+/// current or parent directory) written as `_`
+fn sanitize(value: &str) -> String {
+    if !value.is_empty() && value.chars().all(|char| char == '.') {
+        return String::from("_");
+    }
+    value
+        .chars()
+        .map(|char| match RESERVED.contains(&char) || char.is_control() {
+            true => '_',
+            false => char,
+        })
+        .collect()
+}
+
+/// Path of a rendered layout, the spaces and dots some filesystems don't allow at the end of names
+/// trimmed and names left empty (e.g. by missing tags) written as `_`
+fn clean(rendered: &str) -> PathBuf {
+    Path::new(rendered)
+        .components()
+        .map(|component| match component {
+            Component::Normal(name) => {
+                let name = name.to_string_lossy();
+                let name = name.trim().trim_end_matches('.');
+                PathBuf::from(if name.is_empty() { "_" } else { name })
+            }
+            component => PathBuf::from(component.as_os_str()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tag::details::TagDetails;
+    use crate::utils::moves::{
+        apply, clean, is_relative, log, plan, report, sanitize, to_csv, Move,
+    };
+    use crate::utils::template::Template;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn ensure_fn_sanitize_works_as_expected_1() {
+        assert_eq!(sanitize("AC/DC: Live?"), "AC_DC_ Live_");
+        assert_eq!(sanitize("Beyoncé"), "Beyoncé");
+        assert_eq!(sanitize(".."), "_");
+    }
+
+    #[test]
+    fn ensure_fn_clean_works_as_expected_1() {
+        assert_eq!(
+            clean("/music/ Joji /../2020 - Nectar./01 Ew.mp3"),
+            PathBuf::from("/music/Joji/../2020 - Nectar/01 Ew.mp3")
+        );
+        assert_eq!(
+            clean("/music/ /- Nectar"),
+            PathBuf::from("/music/_/- Nectar")
+        );
+    }
+
+    #[test]
+    fn ensure_fn_plan_works_as_expected_1() {
+        let layout =
+            Template::parse("/music/{albumartist|Unknown}/{track:02} {title}.{ext}").unwrap();
+        let song = |path: &str, track: &str, title: &str| TagDetails {
+            path: path.to_string(),
+            album_artist: Some(String::from("AC/DC")),
+            track: Some(track.to_string()),
+            title: Some(title.to_string()),
+            ..Default::default()
+        };
+        let songs = vec![
+            song("/in/1.mp3", "1", "Hells Bells"),
+            song("/in/2.mp3", "2", "Shoot to Thrill"),
+            song("/in/3.mp3", "2", "shoot to thrill"),
+            song(
+                "/music/AC_DC/04 Given the Dog a Bone.mp3",
+                "4",
+                "Given the Dog a Bone",
+            ),
+        ];
+
+        let moves = plan(&songs, &layout, None);
+
+        assert_eq!(
+            moves,
+            vec![
+                Move {
+                    from: String::from("/in/1.mp3"),
+                    to: String::from("/music/AC_DC/01 Hells Bells.mp3"),
+                    collision: false,
+                },
+                Move {
+                    from: String::from("/in/2.mp3"),
+                    to: String::from("/music/AC_DC/02 Shoot to Thrill.mp3"),
+                    collision: true,
+                },
+                Move {
+                    from: String::from("/in/3.mp3"),
+                    to: String::from("/music/AC_DC/02 shoot to thrill.mp3"),
+                    collision: true,
+                },
+            ]
+        );
+        assert_eq!(
+            report(&moves[..2]),
+            "/in/1.mp3 -> /music/AC_DC/01 Hells Bells.mp3\n\
+             collision: /in/2.mp3 -> /music/AC_DC/02 Shoot to Thrill.mp3\n\n\
+             1 moves, 1 collisions (not moved)"
+        );
+        assert_eq!(
+            to_csv(&moves[..1]),
+            r#""from","to","collision"
+"/in/1.mp3","/music/AC_DC/01 Hells Bells.mp3","false""#
+        );
+    }
+
+    #[test]
+    fn ensure_fn_plan_works_as_expected_2() {
+        let layout = Template::parse("{artist|Unknown}/{title}.{ext}").unwrap();
+        let songs = vec![TagDetails {
+            path: String::from("/music/in/1.mp3"),
+            title: Some(String::from("Ew")),
+            ..Default::default()
+        }];
+
+        let moves = plan(&songs, &layout, Some(Path::new("/music")));
+
+        assert!(is_relative(&layout));
+        assert!(!is_relative(&Template::parse("/music/{artist}").unwrap()));
+        assert_eq!(moves[0].to, "/music/Unknown/Ew.mp3");
+    }
+
+    #[test]
+    fn ensure_fn_apply_works_as_expected_1() {
+        let dir = std::env::temp_dir().join("playlist-maker-apply-moves-1");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let songs = ["1.mp3", "2.mp3", "taken.mp3"]
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                std::fs::write(&path, name).unwrap();
+                TagDetails {
+                    path: path.to_string_lossy().to_string(),
+                    title: Some(name.replace(".mp3", "")),
+                    album: Some(String::from("Nectar")),
+                    ..Default::default()
+                }
+            })
+            .collect::<Vec<TagDetails>>();
+        std::fs::write(dir.join("taken"), "").unwrap();
+        let layout = Template::parse(&format!(
+            "{}/{{album}}/{{title}}.{{ext}}",
+            dir.to_string_lossy()
+        ))
+        .unwrap();
+        let mut layout_songs = songs.clone();
+        layout_songs[2].album = None;
+        let other = Template::parse(&format!("{}/{{album|taken}}", dir.to_string_lossy())).unwrap();
+
+        let mut moves = plan(&layout_songs[..2], &layout, None);
+        moves.extend(plan(&layout_songs[2..], &other, None));
+        let (moved, failed) = apply(&moves);
+        let log_file = dir.join("undo.csv");
+        log(&log_file, &moved).unwrap();
+        log(&log_file, &moved[..1]).unwrap();
+        let logged = std::fs::read_to_string(&log_file).unwrap();
+        let nectar = dir.join("Nectar");
+        let first = std::fs::read_to_string(nectar.join("1.mp3")).unwrap();
+        let left = Path::new(&songs[0].path).exists();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(moved.len(), 2);
+        assert!(moves[2].collision);
+        assert!(failed.is_empty());
+        assert_eq!(first, "1.mp3");
+        assert!(!left);
+        assert_eq!(logged.lines().count(), 4);
+        assert_eq!(logged.lines().next(), Some(r#""from","to""#));
+    }
+
+    #[test]
+    fn ensure_fn_apply_works_as_expected_2() {
+        let dir = std::env::temp_dir().join("playlist-maker-apply-moves-2");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let (from, to) = (dir.join("1.mp3"), dir.join("2.mp3"));
+        std::fs::write(&from, "1").unwrap();
+        // put there after the moves were planned
+        std::fs::write(&to, "2").unwrap();
+        let moves = vec![Move {
+            from: from.to_string_lossy().to_string(),
+            to: to.to_string_lossy().to_string(),
+            collision: false,
+        }];
+
+        let (moved, failed) = apply(&moves);
+        let contents = (
+            std::fs::read_to_string(&from).unwrap(),
+            std::fs::read_to_string(&to).unwrap(),
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(moved.is_empty());
+        assert_eq!(failed, vec![moves[0].from.to_owned()]);
+        assert_eq!(contents, (String::from("1"), String::from("2")));
+    }
+}
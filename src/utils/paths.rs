@@ -0,0 +1,124 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+pub fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Deepest directory that contains all the paths
+pub fn common_root<'a>(paths: impl Iterator<Item = &'a str>) -> Option<PathBuf> {
+    common_dir(paths.map(|path| {
+        absolute(Path::new(path))
+            .parent()
+            .map(|dir| dir.to_path_buf())
+            .unwrap_or_default()
+    }))
+}
+
+/// Deepest directory that contains all the directories
+pub fn common_dir(dirs: impl Iterator<Item = PathBuf>) -> Option<PathBuf> {
+    dirs.map(|dir| absolute(&dir)).reduce(|root, dir| {
+        root.components()
+            .zip(dir.components())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a)
+            .collect()
+    })
+}
+
+/// Absolute path without `.` and `..` components, resolved without accessing the filesystem
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in absolute(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Absolute path with symlinks resolved, only for files that exist
+pub fn canonical(path: &Path) -> Option<PathBuf> {
+    fs::canonicalize(path).ok()
+}
+
+/// Path relative to `base`, an empty base (e.g. the parent of a bare file name) being the current
+/// directory
+pub fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let base = match base.as_os_str().is_empty() {
+        true => Path::new("."),
+        false => base,
+    };
+    pathdiff::diff_paths(absolute(path), absolute(base)).unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Hidden file next to `path` where its new content is written before replacing it, so a failed
+/// write never leaves a half-written file behind
+pub fn temporary(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.pl-mker.tmp", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::paths::{absolute, common_root, normalize, relative_to};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn ensure_fn_common_root_works_as_expected_1() {
+        let paths = vec![
+            "/home/a/Music/Joji/Ballads 1/01. Attention.mp3",
+            "/home/a/Music/Joji/Nectar/02. Modus.mp3",
+            "/home/a/Music/Tom Misch/Geography/01. Before Paris.mp3",
+        ];
+
+        let root = common_root(paths.into_iter());
+
+        assert_eq!(root, Some(PathBuf::from("/home/a/Music")));
+    }
+
+    #[test]
+    fn ensure_fn_common_root_works_as_expected_2() {
+        let root = common_root(vec!["test-data/songs/1.mp3"].into_iter());
+
+        assert_eq!(root, Some(absolute(Path::new("test-data/songs"))));
+    }
+
+    #[test]
+    fn ensure_fn_common_root_works_as_expected_3() {
+        assert_eq!(common_root(Vec::<&str>::new().into_iter()), None);
+    }
+
+    #[test]
+    fn ensure_fn_relative_to_works_as_expected_1() {
+        let path = relative_to(
+            Path::new("/home/a/Music/Joji/Nectar/02. Modus.mp3"),
+            Path::new("/home/a/Playlists"),
+        );
+
+        assert_eq!(path, PathBuf::from("../Music/Joji/Nectar/02. Modus.mp3"));
+    }
+
+    #[test]
+    fn ensure_fn_relative_to_works_as_expected_2() {
+        let path = relative_to(&absolute(Path::new("songs/1.mp3")), Path::new(""));
+
+        assert_eq!(path, PathBuf::from("songs/1.mp3"));
+    }
+
+    #[test]
+    fn ensure_fn_normalize_works_as_expected_1() {
+        assert_eq!(
+            normalize(Path::new("/home/a/./Music/../Music/1.mp3")),
+            PathBuf::from("/home/a/Music/1.mp3")
+        );
+        assert_eq!(
+            normalize(Path::new("./test-data/songs/1.mp3")),
+            absolute(Path::new("test-data/songs/1.mp3"))
+        );
+    }
+}
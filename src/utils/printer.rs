@@ -1,8 +1,23 @@
+use crate::index;
+use crate::index::IndexFormat;
+use crate::playlist::{m3u, pls, xspf};
+use crate::query::check::Problem;
+use crate::query::duplicates::Duplicates;
 use crate::query::processor::QueryType;
+use crate::query::stats::Grouping;
+use crate::query::{albums, check, duplicates, stats};
 use crate::tag::details::TagDetails;
+use crate::tag::writer;
+use crate::tag::writer::{Assignment, Change};
+use crate::utils::moves;
+use crate::utils::moves::Move;
+use crate::utils::paths::{absolute, relative_to};
+use crate::utils::template::Template;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Default, PartialEq, Debug)]
 pub enum Output {
@@ -11,45 +26,363 @@ pub enum Output {
     Terminal,
 }
 
+/// How the paths of the songs are written in a playlist
+#[derive(Default, PartialEq, Debug)]
+pub enum PathStyle {
+    #[default]
+    AsIs,
+    Absolute,
+    /// Relative to the directory of the output file (or the current one if printing to terminal)
+    Relative,
+    /// Relative to the library root, under the given prefix
+    Prefix(PathBuf),
+}
+
+/// Layout of the output
+#[derive(Default, PartialEq, Debug)]
+pub enum Format {
+    /// One path per line
+    #[default]
+    Plain,
+    /// Extended M3U, each path preceded by an `#EXTINF` line with the duration and name of the song
+    M3uExt,
+    Pls,
+    Xspf,
+    /// Array with every field of the songs, for both playlists and indexes
+    Json,
+    /// Same as `Json` but with one song per line
+    Ndjson,
+    /// Header and one row per song with every field (the index itself for indexes)
+    Csv,
+    /// One line per song written with the template, for both playlists and indexes
+    Template(Template),
+}
+
 #[derive(Default, PartialEq, Debug)]
 pub struct Printer {
     pub output: Output,
     pub print_type: QueryType,
+    pub path_style: PathStyle,
+    pub format: Format,
+    /// Name of the playlist, written by extended M3U and XSPF playlists
+    pub title: Option<String>,
+    /// Directory the songs were read from, paths of indexes are stored relative to it and
+    /// `PathStyle::Prefix` replaces it (the deepest directory containing the songs when unknown)
+    pub library_root: Option<PathBuf>,
 }
 
 impl Printer {
-    fn format(&self, info: &[TagDetails]) -> String {
-        let content = info
-            .iter()
-            .map(|tag| match self.print_type {
-                QueryType::Play => tag.path.clone(),
-                QueryType::Index => tag.to_string(),
+    /// Extension of the files written by the printer
+    pub fn extension(&self) -> &str {
+        match (&self.print_type, &self.format) {
+            (_, Format::Json) => "json",
+            (_, Format::Ndjson) => "ndjson",
+            (_, Format::Csv) => "csv",
+            (QueryType::Stats(_), _) | (_, Format::Template(_)) => "txt",
+            (
+                QueryType::Albums { .. }
+                | QueryType::Duplicates
+                | QueryType::Check
+                | QueryType::Set(_)
+                | QueryType::Move(_),
+                Format::Plain,
+            ) => "txt",
+            (QueryType::Index, _) => "csv",
+            (_, Format::Plain | Format::M3uExt) => "m3u",
+            (_, Format::Pls) => "pls",
+            (_, Format::Xspf) => "xspf",
+        }
+    }
+
+    /// Moves of the songs asked by a `Move` query, none for other queries. They are planned once
+    /// and given to `print` and to the moves made, so both see the same destinations
+    pub fn plan(&self, info: &[TagDetails]) -> Vec<Move> {
+        match &self.print_type {
+            QueryType::Move(layout) => moves::plan(info, layout, self.library_root.as_deref()),
+            _ => vec![],
+        }
+    }
+
+    /// Output of the query in the asked layout, `None` when it can't be written in it. `planned`
+    /// are the moves of a `Move` query, from `plan`
+    fn format(&self, info: &[TagDetails], planned: &[Move]) -> Option<String> {
+        match &self.print_type {
+            QueryType::Stats(grouping) => self.format_stats(info, grouping),
+            QueryType::Albums { complete } => self.format_albums(info, *complete),
+            QueryType::Duplicates => self.format_duplicates(info),
+            QueryType::Check => self.format_problems(info),
+            QueryType::Set(assignments) => self.format_changes(info, assignments),
+            QueryType::Move(_) => self.format_moves(info, planned),
+            QueryType::Play | QueryType::Index => self.format_songs(info),
+        }
+    }
+
+    fn format_stats(&self, info: &[TagDetails], grouping: &Grouping) -> Option<String> {
+        let groups = stats::group(info, grouping);
+        match self.format {
+            Format::Json | Format::Ndjson => self.json(&groups),
+            Format::Csv => Some(stats::to_csv(&groups)),
+            _ => Some(stats::table(grouping, &groups)),
+        }
+    }
+
+    fn format_albums(&self, info: &[TagDetails], complete: bool) -> Option<String> {
+        if !self.is_report() {
+            // other layouts write the songs of the albums, album after album
+            return self.format_songs(&albums::songs(albums::group(info, complete)));
+        }
+        let albums = albums::group(&self.records(info), complete);
+        match self.format {
+            Format::Plain => Some(albums::table(&albums)),
+            Format::Csv => Some(albums::to_csv(&albums)),
+            _ => self.json(&albums),
+        }
+    }
+
+    fn format_duplicates(&self, info: &[TagDetails]) -> Option<String> {
+        let duplicates = duplicates::find(info);
+        if !self.is_report() {
+            // other layouts write the copies, group after group
+            return self.format_songs(&duplicates::songs(duplicates));
+        }
+        let paths = self.written_paths(info);
+        let duplicates = duplicates
+            .into_iter()
+            .map(|group| {
+                Some(Duplicates {
+                    songs: rewrite(group.songs, &paths)?,
+                    ..group
+                })
+            })
+            .collect::<Option<Vec<Duplicates>>>()?;
+        match self.format {
+            Format::Plain => Some(duplicates::report(&duplicates)),
+            Format::Csv => Some(duplicates::to_csv(&duplicates)),
+            _ => self.json(&duplicates),
+        }
+    }
+
+    fn format_problems(&self, info: &[TagDetails]) -> Option<String> {
+        let problems = check::check(info);
+        if !self.is_report() {
+            // other layouts write the songs having problems
+            return self.format_songs(&check::songs(problems));
+        }
+        let paths = self.written_paths(info);
+        let problems = problems
+            .into_iter()
+            .map(|problem| {
+                Some(Problem {
+                    songs: rewrite(problem.songs, &paths)?,
+                    ..problem
+                })
+            })
+            .collect::<Option<Vec<Problem>>>()?;
+        match self.format {
+            Format::Plain => Some(check::report(&problems)),
+            Format::Csv => Some(check::to_csv(&problems)),
+            _ => self.json(&problems),
+        }
+    }
+
+    fn format_changes(&self, info: &[TagDetails], assignments: &[Assignment]) -> Option<String> {
+        let changes = writer::changes(info, assignments);
+        if !self.is_report() {
+            // other layouts write the songs with changed tags
+            let changed = changes
+                .into_iter()
+                .map(|change| change.path)
+                .collect::<HashSet<String>>();
+            return self.format_songs(
+                &info
+                    .iter()
+                    .filter(|song| changed.contains(&song.path))
+                    .cloned()
+                    .collect::<Vec<TagDetails>>(),
+            );
+        }
+        let paths = self.written_paths(info);
+        let changes = changes
+            .into_iter()
+            .map(|change| {
+                Some(Change {
+                    path: paths.get(change.path.as_str())?.to_owned(),
+                    ..change
+                })
+            })
+            .collect::<Option<Vec<Change>>>()?;
+        match self.format {
+            Format::Plain => Some(writer::diff(&changes)),
+            Format::Csv => Some(writer::to_csv(&changes)),
+            _ => self.json(&changes),
+        }
+    }
+
+    fn format_moves(&self, info: &[TagDetails], planned: &[Move]) -> Option<String> {
+        match self.format {
+            Format::Plain => Some(moves::report(planned)),
+            Format::Csv => Some(moves::to_csv(planned)),
+            Format::Json | Format::Ndjson => self.json(planned),
+            // other layouts write the songs at the paths they are moved to
+            _ => {
+                let moved = planned
+                    .iter()
+                    .filter(|planned| !planned.collision)
+                    .map(|planned| (planned.from.as_str(), planned.to.as_str()))
+                    .collect::<HashMap<&str, &str>>();
+                self.format_songs(
+                    &info
+                        .iter()
+                        .filter_map(|song| {
+                            Some(TagDetails {
+                                path: moved.get(song.path.as_str())?.to_string(),
+                                ..song.to_owned()
+                            })
+                        })
+                        .collect::<Vec<TagDetails>>(),
+                )
+            }
+        }
+    }
+
+    /// Layouts reports (e.g. problems or changes) are written in, every other layout writes the
+    /// songs of the report instead
+    fn is_report(&self) -> bool {
+        matches!(
+            self.format,
+            Format::Plain | Format::Csv | Format::Json | Format::Ndjson
+        )
+    }
+
+    /// Items as a pretty json array or as one json object per line
+    fn json<T: Serialize>(&self, items: &[T]) -> Option<String> {
+        match self.format {
+            Format::Ndjson => items
+                .iter()
+                .map(|item| serde_json::to_string(item).ok())
+                .collect::<Option<Vec<String>>>()
+                .map(|lines| lines.join("\n")),
+            _ => serde_json::to_string_pretty(items).ok(),
+        }
+    }
+
+    /// Songs in the asked layout, `None` when they can't be written in it
+    fn format_songs(&self, info: &[TagDetails]) -> Option<String> {
+        Some(match (&self.print_type, &self.format) {
+            (_, Format::Json | Format::Ndjson) => self.json(&self.records(info))?,
+            (_, Format::Template(template)) => self
+                .records(info)
+                .iter()
+                .map(|record| template.render(record))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            (QueryType::Index, _) => index::to_csv(self.library_root.as_deref(), info),
+            (_, Format::Csv) => index::csv_rows(&self.records(info)),
+            (_, Format::Plain) => self.paths(info).join("\n"),
+            (_, Format::M3uExt) => m3u::format(self.title.as_deref(), info, self.paths(info)),
+            (_, Format::Pls) => pls::format(info, self.paths(info)),
+            (_, Format::Xspf) => {
+                xspf::format(self.title.as_deref(), info, self.paths(info)).ok()?
+            }
+        })
+    }
+
+    /// Paths of the songs written as asked by their actual path, `info` being all the songs
+    /// (paths can depend on the rest of them)
+    fn written_paths<'a>(&self, info: &'a [TagDetails]) -> HashMap<&'a str, String> {
+        info.iter()
+            .map(|tag| tag.path.as_str())
+            .zip(self.paths(info))
+            .collect()
+    }
+
+    /// Songs with their paths written as asked
+    fn records(&self, info: &[TagDetails]) -> Vec<TagDetails> {
+        info.iter()
+            .zip(self.paths(info))
+            .map(|(tag, path)| TagDetails {
+                path,
+                ..tag.to_owned()
             })
-            .collect::<Vec<String>>()
-            .join("\n");
-        match self.print_type {
-            QueryType::Play => content,
-            QueryType::Index => format!("{}\n{}", TagDetails::headers(), content),
+            .collect()
+    }
+
+    fn paths(&self, info: &[TagDetails]) -> Vec<String> {
+        match &self.path_style {
+            PathStyle::AsIs => info.iter().map(|tag| tag.path.clone()).collect(),
+            PathStyle::Absolute => info
+                .iter()
+                .map(|tag| absolute(Path::new(&tag.path)).to_string_lossy().to_string())
+                .collect(),
+            PathStyle::Relative => {
+                let base = match &self.output {
+                    Output::File(file) => absolute(file)
+                        .parent()
+                        .map_or_else(PathBuf::new, |dir| dir.to_path_buf()),
+                    Output::Terminal => PathBuf::from("."),
+                };
+                info.iter()
+                    .map(|tag| {
+                        relative_to(Path::new(&tag.path), &base)
+                            .to_string_lossy()
+                            .to_string()
+                    })
+                    .collect()
+            }
+            PathStyle::Prefix(prefix) => index::relativize(info, self.library_root.as_deref())
+                .1
+                .iter()
+                .map(|tag| prefix.join(&tag.path).to_string_lossy().to_string())
+                .collect(),
         }
     }
 
-    pub fn print(&self, info: &[TagDetails]) -> Option<()> {
+    pub fn print(&self, info: &[TagDetails], planned: &[Move]) -> Option<()> {
         match &self.output {
-            Output::Terminal => println!("{}", self.format(info)),
+            Output::Terminal => println!("{}", self.format(info, planned)?),
+            Output::File(out)
+                if self.print_type == QueryType::Index
+                    && !matches!(
+                        self.format,
+                        Format::Json | Format::Ndjson | Format::Template(_)
+                    )
+                    && index::format(out) != IndexFormat::Csv =>
+            {
+                index::write(out, self.library_root.as_deref(), info)?
+            }
             Output::File(out) => {
+                let content = self.format(info, planned)?;
                 let mut file = File::create(out).ok()?;
-                writeln!(file, "{}", self.format(info)).ok()?;
+                writeln!(file, "{}", content).ok()?;
             }
         }
         Some(())
     }
 }
 
+/// Songs with the paths they are written as, from `Printer::written_paths`
+fn rewrite(songs: Vec<TagDetails>, paths: &HashMap<&str, String>) -> Option<Vec<TagDetails>> {
+    songs
+        .into_iter()
+        .map(|tag| {
+            Some(TagDetails {
+                path: paths.get(tag.path.as_str())?.to_owned(),
+                ..tag
+            })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::query::processor::QueryType;
+    use crate::query::stats::Grouping;
     use crate::tag::details::TagDetails;
-    use crate::utils::printer::Printer;
+    use crate::tag::r#type::TagType;
+    use crate::utils::paths::absolute;
+    use crate::utils::printer::{Format, Output, PathStyle, Printer};
+    use crate::utils::template::Template;
+    use std::path::{Path, PathBuf};
 
     #[test]
     fn ensure_fn_format_works_as_expected_1() {
@@ -57,7 +390,7 @@ mod tests {
             print_type: QueryType::Play,
             ..Default::default()
         };
-        let output = printer.format(default_songs().as_slice());
+        let output = printer.format(default_songs().as_slice(), &[]).unwrap();
 
         assert_eq!(
             "test-data/songs/1.mp3\ntest-data/songs/2.mp3\ntest-data/songs/3.mp3",
@@ -71,17 +404,350 @@ mod tests {
             print_type: QueryType::Index,
             ..Default::default()
         };
-        let output = printer.format(default_songs().as_slice());
+        let output = printer.format(default_songs().as_slice(), &[]).unwrap();
+
+        assert_eq!(
+            format!(
+                r#"#playlist-maker-index v5
+#library-root {}
+"path","track","title","artist","album","album_artist","year","genre","disc","mb_recording_id","mb_release_id","mb_release_group_id","mb_artist_id","duration","modified","size"
+"1.mp3","1","","","Black","","","","","","","","","","",""
+"2.mp3","","","","Blue","Surf","","","","","","","","","",""
+"3.mp3","","","Cap","","","","","","","","","","","","""#,
+                absolute(Path::new("test-data/songs")).display()
+            ),
+            output
+        )
+    }
+
+    #[test]
+    fn ensure_fn_format_works_as_expected_3() {
+        let printer = Printer {
+            print_type: QueryType::Play,
+            path_style: PathStyle::Prefix(PathBuf::from("/sdcard/Music")),
+            library_root: Some(PathBuf::from("test-data")),
+            ..Default::default()
+        };
+        let output = printer.format(default_songs().as_slice(), &[]).unwrap();
+
+        // the songs are all in `songs`, which is kept since the library root is above it
+        assert_eq!(
+            "/sdcard/Music/songs/1.mp3\n/sdcard/Music/songs/2.mp3\n/sdcard/Music/songs/3.mp3",
+            output
+        )
+    }
+
+    #[test]
+    fn ensure_fn_format_works_as_expected_4() {
+        let printer = Printer {
+            output: Output::File(PathBuf::from("test-data/playlist.m3u")),
+            print_type: QueryType::Play,
+            path_style: PathStyle::Relative,
+            ..Default::default()
+        };
+        let output = printer.format(default_songs().as_slice(), &[]).unwrap();
+
+        assert_eq!("songs/1.mp3\nsongs/2.mp3\nsongs/3.mp3", output)
+    }
+
+    #[test]
+    fn ensure_fn_format_works_as_expected_5() {
+        let printer = Printer {
+            print_type: QueryType::Play,
+            path_style: PathStyle::Absolute,
+            ..Default::default()
+        };
+        let output = printer.format(&default_songs()[..1], &[]).unwrap();
+
+        assert_eq!(
+            absolute(Path::new("test-data/songs/1.mp3")).to_string_lossy(),
+            output
+        )
+    }
+
+    #[test]
+    fn ensure_fn_format_works_as_expected_6() {
+        let mut songs = default_songs();
+        songs[0].title = Some("Hello".to_string());
+        songs[0].duration = Some(215);
+        songs[2].title = Some("Stars".to_string());
+        let printer = Printer {
+            print_type: QueryType::Play,
+            format: Format::M3uExt,
+            ..Default::default()
+        };
+        let output = printer.format(&songs, &[]).unwrap();
+
+        assert_eq!(
+            r#"#EXTM3U
+#EXTINF:215,Hello
+test-data/songs/1.mp3
+#EXTINF:-1,2
+test-data/songs/2.mp3
+#EXTINF:-1,Cap - Stars
+test-data/songs/3.mp3"#,
+            output
+        )
+    }
+
+    #[test]
+    fn ensure_fn_format_works_as_expected_7() {
+        let printer = Printer {
+            print_type: QueryType::Play,
+            path_style: PathStyle::Prefix(PathBuf::from("/sdcard/Music")),
+            format: Format::M3uExt,
+            title: Some("Black".to_string()),
+            ..Default::default()
+        };
+        let output = printer.format(&default_songs()[..1], &[]).unwrap();
+
+        assert_eq!(
+            "#EXTM3U\n#PLAYLIST:Black\n#EXTINF:-1,1\n/sdcard/Music/1.mp3",
+            output
+        )
+    }
+
+    #[test]
+    fn ensure_fn_format_works_as_expected_8() {
+        let mut songs = default_songs();
+        songs[0].duration = Some(215);
+        let printer = Printer {
+            print_type: QueryType::Index,
+            path_style: PathStyle::Prefix(PathBuf::from("/sdcard/Music")),
+            format: Format::Json,
+            ..Default::default()
+        };
+        let output = printer.format(&songs[..1], &[]).unwrap();
+
+        assert_eq!(
+            r#"[
+  {
+    "path": "/sdcard/Music/1.mp3",
+    "track": "1",
+    "title": null,
+    "artist": null,
+    "album": "Black",
+    "album_artist": null,
+    "year": null,
+    "genre": null,
+    "disc": null,
+    "mb_recording_id": null,
+    "mb_release_id": null,
+    "mb_release_group_id": null,
+    "mb_artist_id": null,
+    "duration": 215,
+    "modified": null,
+    "size": null
+  }
+]"#,
+            output
+        )
+    }
+
+    #[test]
+    fn ensure_fn_format_works_as_expected_9() {
+        let printer = Printer {
+            print_type: QueryType::Play,
+            format: Format::Ndjson,
+            ..Default::default()
+        };
+        let output = printer.format(&default_songs(), &[]).unwrap();
+
+        let lines = output.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 3);
+        let song = serde_json::from_str::<TagDetails>(lines[1]).unwrap();
+        assert_eq!(song.path, "test-data/songs/2.mp3");
+        assert_eq!(song.album_artist.as_deref(), Some("Surf"));
+    }
+
+    #[test]
+    fn ensure_fn_format_works_as_expected_10() {
+        let printer = Printer {
+            print_type: QueryType::Play,
+            path_style: PathStyle::Prefix(PathBuf::from("/sdcard/Music")),
+            format: Format::Template(
+                Template::parse("{track:02}. {album|upper|No Album} <{path}>").unwrap(),
+            ),
+            ..Default::default()
+        };
+        let output = printer.format(default_songs().as_slice(), &[]).unwrap();
+
+        assert_eq!(
+            "01. BLACK </sdcard/Music/1.mp3>\n00. BLUE </sdcard/Music/2.mp3>\n00. NO ALBUM </sdcard/Music/3.mp3>",
+            output
+        )
+    }
+
+    #[test]
+    fn ensure_fn_format_works_as_expected_11() {
+        let printer = Printer {
+            print_type: QueryType::Stats(Grouping::Tag(TagType::Album)),
+            format: Format::Ndjson,
+            ..Default::default()
+        };
+        let output = printer.format(default_songs().as_slice(), &[]).unwrap();
+
+        assert_eq!(
+            r#"{"name":"Black","songs":1,"duration":null}
+{"name":"Blue","songs":1,"duration":null}
+{"name":null,"songs":1,"duration":null}"#,
+            output
+        )
+    }
+
+    #[test]
+    fn ensure_fn_format_works_as_expected_12() {
+        let printer = Printer {
+            print_type: QueryType::Play,
+            format: Format::Csv,
+            ..Default::default()
+        };
+        let output = printer.format(&default_songs()[..1], &[]).unwrap();
 
         assert_eq!(
-            r#""path","track","title","artist","album","album_artist","year","genre","disc"
-"test-data/songs/1.mp3","1","","","Black","","","",""
-"test-data/songs/2.mp3","","","","Blue","Surf","","",""
-"test-data/songs/3.mp3","","","Cap","","","","","""#,
+            r#""path","track","title","artist","album","album_artist","year","genre","disc","mb_recording_id","mb_release_id","mb_release_group_id","mb_artist_id","duration","modified","size"
+"test-data/songs/1.mp3","1","","","Black","","","","","","","","","","","""#,
             output
         )
     }
 
+    #[test]
+    fn ensure_fn_format_works_as_expected_13() {
+        let mut songs = default_songs();
+        songs[0].album = Some(String::from("Blue"));
+        songs[0].album_artist = Some(String::from("Surf"));
+        songs[0].track = Some(String::from("2"));
+        songs[1].track = Some(String::from("1"));
+        let printer = Printer {
+            print_type: QueryType::Albums { complete: true },
+            ..Default::default()
+        };
+        let playlist = Printer {
+            format: Format::M3uExt,
+            ..printer
+        };
+
+        assert_eq!(
+            "#EXTM3U\n#EXTINF:-1,2\ntest-data/songs/2.mp3\n#EXTINF:-1,1\ntest-data/songs/1.mp3",
+            playlist.format(&songs, &[]).unwrap()
+        );
+        assert_eq!(playlist.extension(), "m3u");
+    }
+
+    #[test]
+    fn ensure_fn_format_works_as_expected_14() {
+        let mut songs = default_songs();
+        songs[2].title = Some(String::from("Blue "));
+        let songs = &songs[2..];
+        let printer = Printer {
+            print_type: QueryType::Check,
+            format: Format::Ndjson,
+            path_style: PathStyle::Prefix(PathBuf::from("/sdcard/Music")),
+            ..Default::default()
+        };
+        let playlist = Printer {
+            format: Format::Plain,
+            print_type: QueryType::Check,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            r#"{"lint":"whitespace","message":"title start or end with whitespace","songs":["/sdcard/Music/3.mp3"]}"#,
+            printer.format(songs, &[]).unwrap()
+        );
+        assert!(playlist
+            .format(songs, &[])
+            .unwrap()
+            .starts_with("Whitespace: title"));
+        assert_eq!(
+            "test-data/songs/3.mp3",
+            Printer {
+                format: Format::M3uExt,
+                ..playlist
+            }
+            .format(songs, &[])
+            .unwrap()
+            .lines()
+            .last()
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn ensure_fn_extension_works_as_expected_1() {
+        let printer = |print_type, format| Printer {
+            print_type,
+            format,
+            ..Default::default()
+        };
+
+        assert_eq!(printer(QueryType::Play, Format::Plain).extension(), "m3u");
+        assert_eq!(printer(QueryType::Play, Format::M3uExt).extension(), "m3u");
+        assert_eq!(printer(QueryType::Play, Format::Pls).extension(), "pls");
+        assert_eq!(printer(QueryType::Play, Format::Xspf).extension(), "xspf");
+        assert_eq!(printer(QueryType::Index, Format::Xspf).extension(), "csv");
+        assert_eq!(printer(QueryType::Index, Format::Json).extension(), "json");
+        assert_eq!(printer(QueryType::Play, Format::Csv).extension(), "csv");
+        assert_eq!(
+            printer(QueryType::Albums { complete: false }, Format::Plain).extension(),
+            "txt"
+        );
+        assert_eq!(
+            printer(QueryType::Duplicates, Format::Pls).extension(),
+            "pls"
+        );
+        assert_eq!(
+            printer(QueryType::Stats(Grouping::Decade), Format::Plain).extension(),
+            "txt"
+        );
+        assert_eq!(
+            printer(QueryType::Play, Format::Ndjson).extension(),
+            "ndjson"
+        );
+    }
+
+    #[test]
+    fn ensure_fn_format_works_as_expected_15() {
+        let printer = Printer {
+            print_type: QueryType::Albums { complete: false },
+            format: Format::Ndjson,
+            path_style: PathStyle::Prefix(PathBuf::from("/sdcard/Music")),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            r#"{"album":"Black","album_artist":null,"tracks":1,"complete":true,"missing":[],"duration":null,"songs":["/sdcard/Music/1.mp3"]}"#,
+            printer
+                .format(&default_songs(), &[])
+                .unwrap()
+                .lines()
+                .last()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn ensure_fn_plan_works_as_expected_1() {
+        let printer = Printer {
+            print_type: QueryType::Move(Template::parse("/music/{ext}").unwrap()),
+            ..Default::default()
+        };
+
+        let planned = printer.plan(&default_songs());
+
+        assert!(Printer::default().plan(&default_songs()).is_empty());
+        assert_eq!(planned.len(), 3);
+        assert!(planned.iter().all(|planned| planned.collision));
+        assert!(printer
+            .format(&default_songs(), &planned)
+            .unwrap()
+            .ends_with("0 moves, 3 collisions (not moved)"));
+        assert_eq!(
+            printer.format(&default_songs(), &[]).unwrap(),
+            "\n0 moves, 0 collisions (not moved)"
+        );
+    }
+
     fn default_songs() -> Vec<TagDetails> {
         let info1 = TagDetails {
             path: "test-data/songs/1.mp3".to_string(),
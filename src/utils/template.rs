@@ -0,0 +1,308 @@
+use crate::tag::details::TagDetails;
+use std::path::Path;
+
+/// Text written once per song, with `{<field>}` placeholders replaced by the fields of the song.
+///
+/// Placeholders can have a width (`{track:02}` pads with zeros, `{title:30}` with spaces), filters
+/// (`{artist|upper}`, `{title|truncate(20)}`, `{path|basename}`) and a default used when the song
+/// doesn't have the field (`{genre|Unknown}`). `{{` and `}}` are written as literal braces. Fields
+/// are matched ignoring case and underscores (`{albumartist}`) and `{ext}` is the extension of the
+/// file.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum Part {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Debug, Eq, PartialEq)]
+struct Placeholder {
+    field: String,
+    width: Option<Width>,
+    filters: Vec<Filter>,
+    default: Option<String>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+struct Width {
+    size: usize,
+    zeros: bool,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum Filter {
+    Upper,
+    Lower,
+    Basename,
+    Truncate(usize),
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parts = vec![];
+        let mut literal = String::new();
+        let mut rest = text;
+        while let Some(next) = rest.chars().next() {
+            if let Some(after) = rest.strip_prefix("{{") {
+                literal.push('{');
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("}}") {
+                literal.push('}');
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix('{') {
+                let end = after
+                    .find('}')
+                    .ok_or_else(|| format!("template `{}` has an unclosed `{{`!", text))?;
+                if !literal.is_empty() {
+                    parts.push(Part::Text(std::mem::take(&mut literal)));
+                }
+                parts.push(Part::Placeholder(Placeholder::parse(&after[..end])?));
+                rest = &after[end + 1..];
+            } else if next == '}' {
+                return Err(format!("template `{}` has an unopened `}}`!", text));
+            } else {
+                literal.push(next);
+                rest = &rest[next.len_utf8()..];
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Text(literal));
+        }
+        Ok(Template { parts })
+    }
+
+    pub fn render(&self, song: &TagDetails) -> String {
+        self.render_with(song, str::to_string)
+    }
+
+    /// Same as `render` but every value written is passed through `escape` first, e.g. to keep
+    /// separators out of the values written in a path
+    pub fn render_with(&self, song: &TagDetails, escape: impl Fn(&str) -> String) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.to_owned(),
+                Part::Placeholder(placeholder) => escape(&placeholder.render(song)),
+            })
+            .collect()
+    }
+}
+
+impl Placeholder {
+    /// `<field>[:<width>]` followed by `|<filter>` or `|<default>` segments, any segment that
+    /// isn't a filter being the default
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut segments = text.split('|');
+        let head = segments.next().unwrap_or_default();
+        let (field, width) = match head.split_once(':') {
+            Some((field, width)) => (field.trim(), Some(width.trim())),
+            None => (head.trim(), None),
+        };
+        let name = |field: &str| field.replace('_', "").to_lowercase();
+        let field = TagDetails::FIELDS
+            .iter()
+            .chain(&["ext"])
+            .find(|known| name(known) == name(field))
+            .ok_or_else(|| format!("template field `{}` does not exist!", field))?;
+        let width = match width {
+            Some(width) => Some(Width::parse(width).ok_or_else(|| {
+                format!(
+                    "template field `{}` has an invalid width `{}`!",
+                    field, width
+                )
+            })?),
+            None => None,
+        };
+
+        let mut filters = vec![];
+        let mut default = None;
+        for segment in segments {
+            match (Filter::parse(segment.trim()), &default) {
+                (Some(filter), _) => filters.push(filter),
+                (None, None) => default = Some(segment.to_string()),
+                (None, Some(_)) => {
+                    return Err(format!(
+                        "template field `{}` has more than one default!",
+                        field
+                    ))
+                }
+            }
+        }
+        Ok(Placeholder {
+            field: field.to_string(),
+            width,
+            filters,
+            default,
+        })
+    }
+
+    /// The default replaces missing and empty values, then filters are applied in order and the
+    /// outcome is padded to the width
+    fn render(&self, song: &TagDetails) -> String {
+        let value = song
+            .field(&self.field)
+            .filter(|value| !value.is_empty())
+            .or_else(|| self.default.to_owned())
+            .unwrap_or_default();
+        let value = self
+            .filters
+            .iter()
+            .fold(value, |value, filter| filter.apply(value));
+        match &self.width {
+            Some(Width { size, zeros: true }) => format!("{:0>size$}", value, size = size),
+            Some(Width { size, zeros: false }) => format!("{:<size$}", value, size = size),
+            None => value,
+        }
+    }
+}
+
+impl Width {
+    fn parse(text: &str) -> Option<Self> {
+        Some(Width {
+            size: text.parse().ok()?,
+            zeros: text.len() > 1 && text.starts_with('0'),
+        })
+    }
+}
+
+impl Filter {
+    fn parse(text: &str) -> Option<Self> {
+        match text {
+            "upper" => Some(Filter::Upper),
+            "lower" => Some(Filter::Lower),
+            "basename" => Some(Filter::Basename),
+            _ => text
+                .strip_prefix("truncate(")?
+                .strip_suffix(')')?
+                .trim()
+                .parse()
+                .ok()
+                .map(Filter::Truncate),
+        }
+    }
+
+    fn apply(&self, value: String) -> String {
+        match self {
+            Filter::Upper => value.to_uppercase(),
+            Filter::Lower => value.to_lowercase(),
+            Filter::Basename => Path::new(&value)
+                .file_name()
+                .map_or_else(|| value.to_owned(), |e| e.to_string_lossy().to_string()),
+            Filter::Truncate(size) => value.chars().take(*size).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tag::details::TagDetails;
+    use crate::utils::template::Template;
+
+    #[test]
+    fn ensure_fn_render_works_as_expected_1() {
+        let template = Template::parse("{track:02} - {artist} - {title} ({year})").unwrap();
+
+        assert_eq!(
+            template.render(&default_song()),
+            "06 - Drake - Passionfruit (2017)"
+        );
+    }
+
+    #[test]
+    fn ensure_fn_render_works_as_expected_2() {
+        let template = Template::parse("{genre|Unknown} / {album| - } / {disc|0}").unwrap();
+
+        assert_eq!(template.render(&default_song()), "Unknown / More Life / 0");
+    }
+
+    #[test]
+    fn ensure_fn_render_works_as_expected_3() {
+        let template =
+            Template::parse("{artist|upper} {title|truncate(6)|lower} {path|basename}").unwrap();
+
+        assert_eq!(template.render(&default_song()), "DRAKE passio 1.mp3");
+    }
+
+    #[test]
+    fn ensure_fn_render_works_as_expected_4() {
+        let template =
+            Template::parse("{{{title:14}}} {genre|upper|unknown} ({duration}s)").unwrap();
+
+        assert_eq!(
+            template.render(&default_song()),
+            "{Passionfruit  } UNKNOWN (215s)"
+        );
+    }
+
+    #[test]
+    fn ensure_fn_render_works_as_expected_5() {
+        let template = Template::parse("Beyoncé ☆ {title|truncate(3)}").unwrap();
+        let song = TagDetails {
+            title: Some(String::from("Déjà Vu")),
+            ..Default::default()
+        };
+
+        assert_eq!(template.render(&song), "Beyoncé ☆ Déj");
+    }
+
+    #[test]
+    fn ensure_fn_render_works_as_expected_6() {
+        let template =
+            Template::parse("{AlbumArtist|Unknown}/{album}/{Track:02} {title}.{ext}").unwrap();
+
+        assert_eq!(
+            template.render(&default_song()),
+            "Unknown/More Life/06 Passionfruit.mp3"
+        );
+        assert_eq!(
+            template.render_with(&default_song(), |value| value.replace(' ', "_")),
+            "Unknown/More_Life/06 Passionfruit.mp3"
+        );
+    }
+
+    #[test]
+    fn ensure_fn_parse_works_as_expected_1() {
+        assert_eq!(
+            Template::parse("{rating}"),
+            Err(String::from("template field `rating` does not exist!"))
+        );
+        assert_eq!(
+            Template::parse("{track:two}"),
+            Err(String::from(
+                "template field `track` has an invalid width `two`!"
+            ))
+        );
+        assert_eq!(
+            Template::parse("{genre|Rap|Pop}"),
+            Err(String::from(
+                "template field `genre` has more than one default!"
+            ))
+        );
+        assert_eq!(
+            Template::parse("{title"),
+            Err(String::from("template `{title` has an unclosed `{`!"))
+        );
+        assert_eq!(
+            Template::parse("title}"),
+            Err(String::from("template `title}` has an unopened `}`!"))
+        );
+    }
+
+    fn default_song() -> TagDetails {
+        TagDetails {
+            path: "test-data/songs/1.mp3".to_string(),
+            track: Some(String::from("6")),
+            title: Some(String::from("Passionfruit")),
+            artist: Some(String::from("Drake")),
+            album: Some(String::from("More Life")),
+            year: Some(String::from("2017")),
+            genre: Some(String::new()),
+            duration: Some(215),
+            ..Default::default()
+        }
+    }
+}